@@ -0,0 +1,66 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use color_print::cformat;
+use jatr::export::{self, ExportFormat};
+use jatr::reader::TaskFileReadError;
+use jatr::tasks::TaskFile;
+use std::fs;
+
+pub fn command() -> clap::Command {
+    clap::Command::new("export")
+        .about("Translates the tasks into a Makefile or CI pipeline")
+        .arg(
+            clap::arg!(format: --format <FORMAT> "Format to export to")
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    ExportFormat::NAMES,
+                ))
+                .required(true),
+        )
+        .arg(
+            clap::arg!(output: -o --output <FILE> "File to write to, defaults to stdout")
+                .value_parser(clap::value_parser!(Utf8PathBuf)),
+        )
+}
+
+pub fn run(
+    matches: &clap::ArgMatches,
+    path: &Utf8Path,
+    task_file: &Result<TaskFile, TaskFileReadError>,
+) -> i32 {
+    let task_file = match task_file {
+        Ok(task_file) => task_file,
+        Err(e) => {
+            eprintln!("{}: {}: {}", cformat!("<red,bold>error</>"), path, e);
+            return 1;
+        }
+    };
+
+    let format = matches
+        .get_one::<String>("format")
+        .and_then(|name| ExportFormat::from_name(name))
+        .unwrap();
+
+    // Tasks that cannot be translated run through jatr from the task file's directory
+    let jatr = match path.file_name() {
+        Some("tasks.kdl") | None => String::from("jatr"),
+        Some(file_name) => format!("jatr -f {}", file_name),
+    };
+
+    let exported = export::export(task_file, format, &jatr);
+
+    match matches.get_one::<Utf8PathBuf>("output") {
+        Some(output) => match fs::write(output, exported) {
+            Ok(()) => {
+                println!("Exported {}", output);
+                0
+            }
+            Err(e) => {
+                eprintln!("{}: {}: {}", cformat!("<red,bold>error</>"), output, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", exported);
+            0
+        }
+    }
+}
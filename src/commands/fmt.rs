@@ -0,0 +1,75 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use color_print::cformat;
+use jatr::reader::Format;
+use jatr::TaskFileReadError;
+use kdl::KdlDocument;
+use std::fs;
+
+pub fn command() -> clap::Command {
+    clap::Command::new("fmt")
+        .about("Formats the task file canonically")
+        .arg(
+            clap::arg!(files: [FILES] "Files to format, defaults to the task file")
+                .num_args(0..)
+                .value_parser(clap::value_parser!(Utf8PathBuf)),
+        )
+        .arg(
+            clap::arg!(check: --check "Only reports files that are not formatted")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+pub fn run(matches: &clap::ArgMatches, path: &Utf8Path) -> i32 {
+    let files: Vec<&Utf8Path> = match matches.get_many::<Utf8PathBuf>("files") {
+        Some(files) => files.map(|f| f.as_path()).collect(),
+        None => vec![path],
+    };
+    let check = matches.get_flag("check");
+
+    let mut exit_code = 0;
+    for file in files {
+        if Format::from_path(file) != Format::Kdl {
+            eprintln!(
+                "{}: {}: only KDL task files can be formatted",
+                cformat!("<red,bold>error</>"),
+                file
+            );
+            exit_code = 1;
+            continue;
+        }
+
+        match format_file(file, check) {
+            Ok(false) => {}
+            Ok(true) if check => {
+                println!("{}", cformat!("<yellow>{}: not formatted</>", file));
+                exit_code = 1;
+            }
+            Ok(true) => println!("Formatted {}", file),
+            Err(e) => {
+                eprintln!("{}: {}: {}", cformat!("<red,bold>error</>"), file, e);
+                exit_code = 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+/// Formats a single file, returns whether its content changed
+fn format_file(file: &Utf8Path, check: bool) -> Result<bool, TaskFileReadError> {
+    let content = fs::read_to_string(file)?;
+    let mut document: KdlDocument = content.parse()?;
+
+    jatr::writer::format(&mut document);
+
+    let formatted = document.to_string();
+    if formatted == content {
+        return Ok(false);
+    }
+
+    if !check {
+        fs::write(file, formatted)?;
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,15 @@
+use color_print::cformat;
+
+pub fn command() -> clap::Command {
+    clap::Command::new("lsp").about("Runs a language server for task files over stdio")
+}
+
+pub fn run() -> i32 {
+    match jatr::lsp::serve() {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {}", cformat!("<red,bold>error</>"), e);
+            1
+        }
+    }
+}
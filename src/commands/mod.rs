@@ -0,0 +1,91 @@
+mod export;
+mod fmt;
+mod lsp;
+mod run;
+mod schema;
+mod validate;
+
+use jatr::reader::TaskFileReadError;
+use jatr::tasks::TaskFile;
+use camino::Utf8Path;
+use color_print::{cformat, cstr};
+
+pub fn builtin_commands() -> Vec<clap::Command> {
+    vec![
+        export::command(),
+        fmt::command(),
+        lsp::command(),
+        run::command(),
+        schema::command(),
+        validate::command(),
+    ]
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_commands().iter().any(|c| c.get_name() == name)
+}
+
+/// Names and aliases of public tasks taken by a built-in command, with the task's key. Those
+/// tasks can only be run with `jatr run <task>`
+pub fn shadowed(task_file: &TaskFile) -> Vec<(&String, &String)> {
+    task_file
+        .tasks
+        .iter()
+        .filter(|(_, task)| !task.private)
+        .flat_map(|(key, task)| {
+            std::iter::once(key)
+                .chain(&task.aliases)
+                .map(move |name| (key, name))
+        })
+        .filter(|(_, name)| is_builtin(name))
+        .collect()
+}
+
+/// Message for a task name or alias taken by a built-in command
+pub fn shadowed_message(key: &str, name: &str) -> String {
+    format!(
+        "'{}' of task '{}' is a built-in command, run the task with `jatr run {}`",
+        name, key, key
+    )
+}
+
+/// Arguments of task subcommands: variable overrides and the arguments after `--`
+pub fn task_args(command: clap::Command) -> clap::Command {
+    command
+        .arg(clap::arg!(
+            assignments: [ASSIGNMENT] ... "Overrides variables, as NAME=VALUE"
+        ))
+        .arg(clap::arg!(args: [ARGS] ... "Passed to templates as `args`").last(true))
+}
+
+pub fn help() -> String {
+    let commands = builtin_commands();
+    let width = commands.iter().map(|c| c.get_name().len()).max().unwrap_or(0);
+
+    let mut help = String::from(cstr!("<bold><underline>Commands:</underline></bold>"));
+    for command in commands {
+        help.push_str(&cformat!(
+            "\n  <bold>{:width$}</bold>  {}",
+            command.get_name(),
+            command.get_about().map(|a| a.to_string()).unwrap_or_default()
+        ));
+    }
+
+    help
+}
+
+pub fn run(
+    name: &str,
+    matches: &clap::ArgMatches,
+    path: &Utf8Path,
+    task_file: &Result<TaskFile, TaskFileReadError>,
+) -> i32 {
+    match name {
+        "export" => export::run(matches, path, task_file),
+        "fmt" => fmt::run(matches, path),
+        "lsp" => lsp::run(),
+        "schema" => schema::run(matches),
+        "validate" => validate::run(matches, path, task_file),
+        _ => unreachable!("Unknown command '{}'", name),
+    }
+}
@@ -0,0 +1,9 @@
+/// Runs a task by name or alias, including tasks named like a built-in command. Arguments are
+/// those of a task subcommand, the task itself is run by `main`
+pub fn command() -> clap::Command {
+    super::task_args(
+        clap::Command::new("run")
+            .about("Runs a task, also one named like a built-in command")
+            .arg(clap::arg!(task: <TASK> "Task name or alias")),
+    )
+}
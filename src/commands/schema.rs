@@ -0,0 +1,47 @@
+use camino::Utf8PathBuf;
+use color_print::cformat;
+use jatr::schema;
+use std::fs;
+
+pub fn command() -> clap::Command {
+    clap::Command::new("schema")
+        .about("Prints a schema of the task file dialect for editors")
+        .arg(
+            clap::arg!(format: --format <FORMAT> "Schema to generate")
+                .value_parser(["json-schema", "kdl-schema", "metadata"])
+                .default_value("json-schema"),
+        )
+        .arg(
+            clap::arg!(output: -o --output <FILE> "File to write to, defaults to stdout")
+                .value_parser(clap::value_parser!(Utf8PathBuf)),
+        )
+}
+
+pub fn run(matches: &clap::ArgMatches) -> i32 {
+    let schema = match matches.get_one::<String>("format").map(|f| f.as_str()) {
+        Some("kdl-schema") => schema::kdl_schema().to_string(),
+        Some("metadata") => pretty(schema::metadata()),
+        _ => pretty(schema::json_schema()),
+    };
+
+    match matches.get_one::<Utf8PathBuf>("output") {
+        Some(output) => match fs::write(output, schema) {
+            Ok(()) => {
+                println!("Written {}", output);
+                0
+            }
+            Err(e) => {
+                eprintln!("{}: {}: {}", cformat!("<red,bold>error</>"), output, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", schema);
+            0
+        }
+    }
+}
+
+fn pretty(value: serde_json::Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap() + "\n"
+}
@@ -0,0 +1,93 @@
+use jatr::reader::TaskFileReadError;
+use jatr::tasks::TaskFile;
+use jatr::validator::{self, Diagnostic, Severity};
+use camino::Utf8Path;
+use color_print::cformat;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Report<'a> {
+    file: &'a str,
+    valid: bool,
+    diagnostics: &'a [Diagnostic],
+}
+
+pub fn command() -> clap::Command {
+    clap::Command::new("validate")
+        .about("Statically checks the task file and its imports")
+        .arg(
+            clap::arg!(format: --format <FORMAT> "Output format")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+}
+
+pub fn run(
+    matches: &clap::ArgMatches,
+    path: &Utf8Path,
+    task_file: &Result<TaskFile, TaskFileReadError>,
+) -> i32 {
+    let diagnostics = match task_file {
+        Ok(task_file) => {
+            let mut diagnostics = validator::validate(task_file, path.parent().unwrap());
+            for (key, name) in super::shadowed(task_file) {
+                diagnostics.push(Diagnostic::error(
+                    &format!("task '{}'", key),
+                    super::shadowed_message(key, name),
+                ));
+            }
+
+            diagnostics
+        }
+        Err(e) => vec![Diagnostic::error("file", e.to_string())],
+    };
+
+    let valid = !diagnostics.iter().any(|d| d.severity == Severity::Error);
+
+    match matches.get_one::<String>("format").map(|f| f.as_str()) {
+        Some("json") => {
+            let report = Report {
+                file: path.as_str(),
+                valid,
+                diagnostics: &diagnostics,
+            };
+
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        }
+        _ => print_text(path, &diagnostics),
+    }
+
+    match valid {
+        true => 0,
+        false => 1,
+    }
+}
+
+fn print_text(path: &Utf8Path, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => cformat!("<red,bold>error</>"),
+            Severity::Warning => cformat!("<yellow,bold>warning</>"),
+        };
+
+        println!(
+            "{}: {}: {}",
+            severity,
+            cformat!("<white>{}</>", diagnostic.location),
+            diagnostic.message
+        );
+    }
+
+    if diagnostics.is_empty() {
+        println!("{}", cformat!("<green>{}: no problems found</>", path));
+        return;
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    println!("\n{}: {} error(s), {} warning(s)", path, errors, warnings);
+}
@@ -0,0 +1,476 @@
+//! Translates tasks into the build and CI definitions of other tools.
+//!
+//! Commands, `cd` and `task` actions are translated natively, requirements become shell checks
+//! ahead of them. Tasks using anything only jatr understands, like templates, `if` conditions
+//! or tool versions, fall back to running `jatr <task>`. Private tasks cannot be run that way,
+//! so the tasks calling them fall back instead.
+
+use crate::tasks::{Action, MessageLevel, Requirement, RequirementKind, Task, TaskFile};
+use crate::utils::shell_quote;
+use camino::Utf8PathBuf;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Nesting depth after which `task` actions are no longer followed, to stop on cycles
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Make,
+    GithubActions,
+    GitlabCi,
+}
+
+impl ExportFormat {
+    pub const NAMES: &'static [&'static str] = &["make", "github-actions", "gitlab-ci"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "make" => Some(ExportFormat::Make),
+            "github-actions" => Some(ExportFormat::GithubActions),
+            "gitlab-ci" => Some(ExportFormat::GitlabCi),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Make => "make",
+            ExportFormat::GithubActions => "github-actions",
+            ExportFormat::GitlabCi => "gitlab-ci",
+        }
+    }
+}
+
+/// Renders the task file in the given format. `jatr` is the command line used to run tasks
+/// that cannot be translated, e.g. `jatr -f tasks.yaml`
+///
+/// ```
+/// use jatr::export::{export, ExportFormat};
+///
+/// let document = "tasks {\n    test { cmd \"cargo test\"; }\n    ci { task \"test\"; }\n}";
+/// let task_file = jatr::reader::read_format(document.into(), jatr::reader::Format::Kdl).unwrap();
+///
+/// let makefile = export(&task_file, ExportFormat::Make, "jatr");
+/// assert!(makefile.contains("test:\n\tcargo test\n"));
+/// assert!(makefile.contains("ci: test\n"));
+/// ```
+pub fn export(task_file: &TaskFile, format: ExportFormat, jatr: &str) -> String {
+    let exporter = Exporter { task_file, jatr };
+    let header = format!(
+        "# Generated by `jatr export --format {}`. Regenerate it instead of editing by hand.\n",
+        format.name()
+    );
+
+    let body = match format {
+        ExportFormat::Make => exporter.makefile(),
+        ExportFormat::GithubActions => exporter.github_actions(),
+        ExportFormat::GitlabCi => exporter.gitlab_ci(),
+    };
+
+    header + &body
+}
+
+/// Shell command as run by a `cmd` action
+struct Step {
+    command: String,
+    shell: Option<String>,
+    work_dir: Option<Utf8PathBuf>,
+}
+
+enum Item {
+    Step(Step),
+    /// `task` action, with the task key and the working directory it was called from
+    Call(String, Option<Utf8PathBuf>),
+}
+
+struct Exporter<'a> {
+    task_file: &'a TaskFile,
+    jatr: &'a str,
+}
+
+#[derive(Serialize)]
+struct Workflow {
+    name: String,
+    on: Vec<&'static str>,
+    jobs: IndexMap<String, GithubJob>,
+}
+
+#[derive(Serialize)]
+struct GithubJob {
+    name: String,
+    #[serde(rename = "runs-on")]
+    runs_on: &'static str,
+    steps: Vec<GithubStep>,
+}
+
+#[derive(Serialize)]
+struct GithubStep {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uses: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    #[serde(rename = "working-directory", skip_serializing_if = "Option::is_none")]
+    working_directory: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GitlabJob {
+    script: Vec<String>,
+}
+
+impl Exporter<'_> {
+    fn makefile(&self) -> String {
+        // Private tasks without a rule are left out, the tasks calling them run through jatr
+        let rules: Vec<_> = self
+            .task_file
+            .tasks
+            .iter()
+            .map(|(name, task)| (name, task, self.make_rule(task, 0)))
+            .filter(|(_, task, rule)| rule.is_some() || !task.private)
+            .collect();
+
+        let names: Vec<String> = rules.iter().map(|(n, _, _)| make_target(n)).collect();
+        let mut makefile = format!("\n.PHONY: {}\n", names.join(" "));
+
+        if let Some((name, _)) = self.default_task() {
+            if names.contains(&make_target(name)) {
+                writeln!(makefile, ".DEFAULT_GOAL := {}", make_target(name)).unwrap();
+            }
+        }
+
+        for (name, task, rule) in rules {
+            makefile.push('\n');
+            if let Some(description) = &task.description {
+                writeln!(makefile, "# {}", description.replace('\n', " ")).unwrap();
+            }
+
+            let (prerequisites, recipe) =
+                rule.unwrap_or_else(|| (vec![], vec![self.fallback(name).replace('$', "$$")]));
+
+            let mut rule = make_target(name) + ":";
+            for prerequisite in prerequisites {
+                rule.push(' ');
+                rule.push_str(&make_target(&prerequisite));
+            }
+            writeln!(makefile, "{}", rule).unwrap();
+
+            for line in recipe {
+                writeln!(makefile, "\t{}", line).unwrap();
+            }
+        }
+
+        makefile
+    }
+
+    /// Leading `task` actions become prerequisites, everything after them recipe lines with
+    /// `$` escaped from make
+    fn make_rule(&self, task: &Task, depth: usize) -> Option<(Vec<String>, Vec<String>)> {
+        let mut prerequisites = vec![];
+        let mut recipe = vec![];
+
+        for item in self.translate(task, depth)? {
+            match item {
+                Item::Call(name, None) if !self.has_make_rule(&name, depth) => return None,
+                Item::Call(name, None) if recipe.is_empty() => prerequisites.push(name),
+                Item::Call(name, None) => recipe.push(format!("$(MAKE) {}", shell_quote(&name))),
+                Item::Call(_, Some(_)) => return None,
+                Item::Step(step) if step.command.contains('\n') => return None,
+                Item::Step(step) => recipe.push(shell_line(&step).replace('$', "$$")),
+            }
+        }
+
+        Some((prerequisites, recipe))
+    }
+
+    fn github_actions(&self) -> String {
+        let jobs = self
+            .public_tasks()
+            .map(|(name, _)| {
+                let mut steps = vec![GithubStep {
+                    uses: Some("actions/checkout@v4"),
+                    run: None,
+                    shell: None,
+                    working_directory: None,
+                }];
+
+                for step in self.inline(name, None, 0) {
+                    steps.push(GithubStep {
+                        uses: None,
+                        run: Some(step.command),
+                        shell: step.shell.map(|shell| github_shell(&shell)),
+                        working_directory: step.work_dir.map(|dir| dir.to_string()),
+                    });
+                }
+
+                let job = GithubJob {
+                    name: name.clone(),
+                    runs_on: "ubuntu-latest",
+                    steps,
+                };
+
+                (github_job_id(name), job)
+            })
+            .collect();
+
+        let workflow = Workflow {
+            name: String::from("tasks"),
+            on: vec!["push", "pull_request", "workflow_dispatch"],
+            jobs,
+        };
+
+        serde_yaml::to_string(&workflow).unwrap()
+    }
+
+    fn gitlab_ci(&self) -> String {
+        let jobs: IndexMap<String, GitlabJob> = self
+            .public_tasks()
+            .map(|(name, _)| {
+                let script = self
+                    .inline(name, None, 0)
+                    .iter()
+                    .map(|step| match &step.work_dir {
+                        // A subshell keeps the directory change from leaking into later lines
+                        Some(_) => format!("({})", shell_line(step)),
+                        None => shell_line(step),
+                    })
+                    .collect();
+
+                (name.clone(), GitlabJob { script })
+            })
+            .collect();
+
+        serde_yaml::to_string(&jobs).unwrap()
+    }
+
+    fn public_tasks(&self) -> impl Iterator<Item = (&String, &Task)> {
+        self.task_file
+            .tasks
+            .iter()
+            .filter(|(_, task)| !task.private)
+    }
+
+    fn default_task(&self) -> Option<(&String, &Task)> {
+        self.resolve(self.task_file.default.as_deref()?)
+    }
+
+    /// Finds a task by key or alias, returning its key
+    fn resolve(&self, name: &str) -> Option<(&String, &Task)> {
+        self.task_file
+            .tasks
+            .iter()
+            .find(|(key, task)| *key == name || task.aliases.iter().any(|a| a == name))
+    }
+
+    /// Whether the called task gets a rule of its own, private tasks only get one when
+    /// they can be translated
+    fn has_make_rule(&self, name: &str, depth: usize) -> bool {
+        match self.task_file.tasks.get(name) {
+            Some(task) if task.private => self.make_rule(task, depth + 1).is_some(),
+            _ => true,
+        }
+    }
+
+    fn fallback(&self, name: &str) -> String {
+        format!("{} {}", self.jatr, shell_quote(name))
+    }
+
+    /// Actions of a task as steps and calls, `None` when the task needs jatr to run. Calling a
+    /// private task that needs jatr needs jatr too
+    fn translate(&self, task: &Task, depth: usize) -> Option<Vec<Item>> {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+
+        let mut items = vec![];
+        let mut work_dir: Option<Utf8PathBuf> = None;
+
+        for requirement in &task.requirements {
+            items.push(Item::Step(Step {
+                command: requirement_check(requirement)?,
+                shell: None,
+                work_dir: None,
+            }));
+        }
+
+        for action in &task.actions {
+            match action {
+                Action::Noop => {}
+                Action::Command(cmd) if is_template(&cmd.command) => return None,
+                Action::Command(cmd) => items.push(Item::Step(Step {
+                    command: cmd.command.clone(),
+                    shell: cmd.shell.clone(),
+                    work_dir: work_dir.clone(),
+                })),
+                Action::If(_) | Action::Confirm(_) | Action::Script(_) | Action::File(_) => {
+                    return None
+                }
+                Action::Echo(message) if is_template(&message.text) => return None,
+                Action::Echo(message) => {
+                    let redirect = match message.level {
+                        MessageLevel::Info => "",
+                        MessageLevel::Warn | MessageLevel::Error => " >&2",
+                    };
+                    items.push(Item::Step(Step {
+                        command: format!("echo {}{}", shell_quote(&message.text), redirect),
+                        shell: None,
+                        work_dir: work_dir.clone(),
+                    }));
+                }
+                Action::Fail(failure) if is_template(&failure.message) => return None,
+                Action::Fail(failure) => items.push(Item::Step(Step {
+                    command: format!(
+                        "echo {} >&2; exit {}",
+                        shell_quote(&failure.message),
+                        failure.code
+                    ),
+                    shell: None,
+                    work_dir: work_dir.clone(),
+                })),
+                Action::Task(call) => {
+                    let (name, called) = self.resolve(&call.name)?;
+                    if called.private {
+                        self.translate(called, depth + 1)?;
+                    }
+                    items.push(Item::Call(name.clone(), work_dir.clone()));
+                }
+                Action::Cd(path) if is_template(path) => return None,
+                Action::Cd(path) => {
+                    work_dir = Some(match work_dir {
+                        Some(dir) => dir.join(path),
+                        None => Utf8PathBuf::from(path),
+                    });
+                }
+            }
+        }
+
+        Some(items)
+    }
+
+    /// Flattens a task and everything it calls into steps, falling back to jatr for each task
+    /// that cannot be translated
+    fn inline(&self, name: &str, work_dir: Option<Utf8PathBuf>, depth: usize) -> Vec<Step> {
+        let fallback = || {
+            vec![Step {
+                command: self.fallback(name),
+                shell: None,
+                work_dir: work_dir.clone(),
+            }]
+        };
+
+        let Some((_, task)) = self.resolve(name) else {
+            return fallback();
+        };
+        let Some(items) = self.translate(task, depth) else {
+            return fallback();
+        };
+
+        let join = |dir: Option<Utf8PathBuf>| match (&work_dir, dir) {
+            (Some(base), Some(dir)) => Some(base.join(dir)),
+            (base, dir) => dir.or(base.clone()),
+        };
+
+        items
+            .into_iter()
+            .flat_map(|item| match item {
+                Item::Step(step) => vec![Step {
+                    work_dir: join(step.work_dir),
+                    ..step
+                }],
+                Item::Call(called, dir) => self.inline(&called, join(dir), depth + 1),
+            })
+            .collect()
+    }
+}
+
+/// Shell command failing with jatr's message when the requirement is not met, `None` for
+/// tool versions since comparing them needs jatr
+fn requirement_check(requirement: &Requirement) -> Option<String> {
+    let (check, problem) = match &requirement.kind {
+        RequirementKind::Tool {
+            version: Some(_), ..
+        } => return None,
+        RequirementKind::Tool {
+            name,
+            version: None,
+        } => (
+            format!("command -v {} >/dev/null", shell_quote(name)),
+            format!("tool '{}' not found in PATH", name),
+        ),
+        RequirementKind::Env(name) if !is_identifier(name) => return None,
+        RequirementKind::Env(name) => (
+            format!("test -n \"${{{}+set}}\"", name),
+            format!("environment variable '{}' is not set", name),
+        ),
+        RequirementKind::File(path) => (
+            format!("test -e {}", shell_quote(path)),
+            format!("file '{}' not found", path),
+        ),
+    };
+
+    let message = match &requirement.hint {
+        Some(hint) => format!("{}, hint: {}", problem, hint),
+        None => problem,
+    };
+
+    Some(format!(
+        "{} || {{ echo {} >&2; exit 1; }}",
+        check,
+        shell_quote(&message.replace('\n', " "))
+    ))
+}
+
+/// Whether the shell can expand `name` as a variable
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_template(text: &str) -> bool {
+    text.contains("{{") || text.contains("{%")
+}
+
+/// Command line running a step with its shell, changing into its directory first
+fn shell_line(step: &Step) -> String {
+    let command = match &step.shell {
+        Some(shell) => format!("{} -c {}", shell, shell_quote(&step.command)),
+        None => step.command.clone(),
+    };
+
+    match &step.work_dir {
+        Some(dir) => format!("cd {} && {}", shell_quote(dir.as_str()), command),
+        None => command,
+    }
+}
+
+fn make_target(name: &str) -> String {
+    name.replace(':', "\\:").replace('$', "$$")
+}
+
+/// GitHub only knows a few shells by name, everything else needs a command template
+fn github_shell(shell: &str) -> String {
+    match shell {
+        "bash" | "sh" | "pwsh" | "python" | "cmd" | "powershell" => String::from(shell),
+        shell => format!("{} {{0}}", shell),
+    }
+}
+
+fn github_job_id(name: &str) -> String {
+    let id: String = name
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '-',
+            },
+        )
+        .collect();
+
+    match id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        true => id,
+        false => format!("_{}", id),
+    }
+}
@@ -0,0 +1,38 @@
+//! jatr reads task definitions from KDL, YAML, TOML or JSON files and runs them.
+//!
+//! The `jatr` binary is a thin command line front end over this crate. Other tools can load
+//! and run the same task files in-process:
+//!
+//! ```no_run
+//! use camino::Utf8Path;
+//! use jatr::{reader, Runner, RunnerEnvironment, RunnerResult};
+//!
+//! let task_file = reader::open_and_read(Utf8Path::new("tasks.kdl")).unwrap();
+//! let task = task_file.find_task("build").expect("task 'build' exists");
+//!
+//! let mut environment = RunnerEnvironment::default();
+//! environment.work_dir(".").unwrap();
+//!
+//! let mut runner = Runner::for_taskfile(&task_file, environment);
+//! match runner.run(task) {
+//!     Ok(RunnerResult::Success) => println!("done"),
+//!     Ok(result) => println!("finished with {:?}", result),
+//!     Err(e) => eprintln!("{}", e),
+//! }
+//! ```
+
+pub mod export;
+pub mod lsp;
+pub mod reader;
+pub mod runner;
+pub mod schema;
+pub mod tasks;
+pub mod validator;
+pub mod writer;
+
+mod utils;
+
+pub use reader::{open_and_read, TaskFileReadError};
+pub use runner::environment::RunnerEnvironment;
+pub use runner::{Runner, RunnerError, RunnerErrorData, RunnerResult};
+pub use tasks::{Task, TaskFile};
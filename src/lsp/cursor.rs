@@ -0,0 +1,263 @@
+use crate::reader::node;
+use crate::reader::Scope;
+
+/// What is being written at the cursor, worked out from the text before it so completion
+/// keeps working while the document does not parse
+#[derive(Debug, PartialEq)]
+pub enum Context {
+    /// Name of a node in the given scope
+    NodeName(Scope),
+    /// Argument or property of the named node. `quoted` is true inside a string
+    Entry { node: String, quoted: bool },
+    /// Inside `{{ }}` or `{% %}` of a string
+    Template,
+}
+
+#[derive(Debug)]
+pub struct Cursor {
+    pub context: Context,
+    /// Task the cursor is in
+    pub task: Option<String>,
+}
+
+struct Frame {
+    /// `None` inside nodes the reader does not know
+    scope: Option<Scope>,
+    task: Option<String>,
+}
+
+/// Works out the context at a byte offset of KDL source. Returns `None` inside comments and
+/// unknown nodes
+pub fn cursor(text: &str, offset: usize) -> Option<Cursor> {
+    let before = &text[..offset];
+    let mut stack = vec![Frame {
+        scope: Some(Scope::Global),
+        task: None,
+    }];
+    // Name of the node being written and whether the name is complete
+    let mut current: Option<String> = None;
+    let mut name_done = false;
+    let mut string: Option<&str> = None;
+
+    let mut index = 0;
+    while index < before.len() {
+        let rest = &before[index..];
+        let c = rest.chars().next().unwrap();
+
+        if rest.starts_with("//") {
+            index += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("/*") {
+            index += rest.find("*/")? + 2;
+            continue;
+        }
+        if rest.starts_with("/-") {
+            index += 2;
+            continue;
+        }
+
+        if c == '"' || (c == '#' && rest.trim_start_matches('#').starts_with('"')) {
+            let (value, length) = match string_token(rest) {
+                Some(token) => token,
+                None => {
+                    string = Some(rest);
+                    break;
+                }
+            };
+
+            if current.is_none() {
+                current = Some(String::from(value));
+            }
+            name_done = true;
+            index += length;
+            continue;
+        }
+
+        match c {
+            '\n' | ';' => (current, name_done) = (None, false),
+            '\\' => {
+                // Line continuation, the node goes on on the next line
+                index += rest.find('\n').map_or(rest.len(), |i| i + 1);
+                continue;
+            }
+            '{' => {
+                let frame = stack.last().unwrap();
+                let scope = match (&frame.scope, &current) {
+                    (Some(scope), Some(name)) => node::node_type_in_scope(name, scope)
+                        .and_then(|node_type| node_type.spec().children.clone()),
+                    _ => None,
+                };
+                let task = match frame.scope {
+                    Some(Scope::Tasks) => current.clone(),
+                    _ => frame.task.clone(),
+                };
+
+                stack.push(Frame { scope, task });
+                (current, name_done) = (None, false);
+            }
+            '}' => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+                (current, name_done) = (None, false);
+            }
+            c if c.is_whitespace() => name_done |= current.is_some(),
+            c => match &mut current {
+                None => current = Some(String::from(c)),
+                Some(name) if !name_done => name.push(c),
+                Some(_) => {}
+            },
+        }
+
+        index += c.len_utf8();
+    }
+
+    let frame = stack.pop().unwrap();
+    let scope = frame.scope?;
+
+    let context = match (string, current) {
+        (Some(string), _) if in_template(string) => Context::Template,
+        (Some(_), Some(node)) if name_done => Context::Entry { node, quoted: true },
+        (Some(_), _) => Context::NodeName(scope),
+        (None, Some(node)) if name_done => Context::Entry {
+            node,
+            quoted: false,
+        },
+        (None, _) => Context::NodeName(scope),
+    };
+
+    Some(Cursor {
+        context,
+        task: frame.task,
+    })
+}
+
+/// Value and length of a complete quoted, raw or multi-line string at the start of `text`
+fn string_token(text: &str) -> Option<(&str, usize)> {
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    let quotes = match text[hashes..].starts_with("\"\"\"") {
+        true => "\"\"\"",
+        false => "\"",
+    };
+    let start = hashes + quotes.len();
+    let closing = format!("{}{}", quotes, "#".repeat(hashes));
+
+    let mut index = start;
+    loop {
+        let rest = text.get(index..)?;
+        if rest.starts_with(&closing) {
+            return Some((&text[start..index], index + closing.len()));
+        }
+
+        let c = rest.chars().next()?;
+        if c == '\n' && quotes == "\"" {
+            // Unterminated single line string, the parser reports it
+            return Some((&text[start..index], index));
+        }
+        index += match c {
+            '\\' if hashes == 0 => 1 + rest[1..].chars().next()?.len_utf8(),
+            c => c.len_utf8(),
+        };
+    }
+}
+
+/// True when the end of an unterminated string lies inside a template tag
+fn in_template(string: &str) -> bool {
+    let open = string.rfind("{{").max(string.rfind("{%"));
+    let close = string.rfind("}}").max(string.rfind("%}"));
+
+    match (open, close) {
+        (Some(open), Some(close)) => open > close,
+        (open, _) => open.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Context and task of a cursor
+    type Expected = Option<(Context, Option<String>)>;
+
+    /// Context and task at the `|` in `text`
+    fn at_marker(text: &str) -> Expected {
+        let offset = text.find('|').unwrap();
+        let text = text.replace('|', "");
+
+        cursor(&text, offset).map(|cursor| (cursor.context, cursor.task))
+    }
+
+    fn entry(node: &str, quoted: bool) -> Context {
+        Context::Entry {
+            node: String::from(node),
+            quoted,
+        }
+    }
+
+    #[test]
+    fn cursor_contexts() {
+        let task = |name: &str| Some(String::from(name));
+        let cases: &[(&str, Expected)] = &[
+            ("|", Some((Context::NodeName(Scope::Global), None))),
+            ("tas|", Some((Context::NodeName(Scope::Global), None))),
+            (
+                "tasks {\n    |",
+                Some((Context::NodeName(Scope::Tasks), None)),
+            ),
+            (
+                "tasks {\n    build {\n        c|",
+                Some((Context::NodeName(Scope::Task), task("build"))),
+            ),
+            (
+                "tasks {\n    build {\n        cmd |",
+                Some((entry("cmd", false), task("build"))),
+            ),
+            (
+                "tasks {\n    build {\n        cmd \"cargo |",
+                Some((entry("cmd", true), task("build"))),
+            ),
+            (
+                "tasks {\n    build {\n        cmd \"cargo {{ tar|",
+                Some((Context::Template, task("build"))),
+            ),
+            (
+                "tasks {\n    build { - \"make\"; }\n    |",
+                Some((Context::NodeName(Scope::Tasks), None)),
+            ),
+            (
+                "tasks {\n    build {\n        cmd \"echo\" // note\n        |",
+                Some((Context::NodeName(Scope::Task), task("build"))),
+            ),
+            ("/* open comment |", None),
+            ("unknown {\n    |", None),
+            (
+                "variables {\n    |",
+                Some((Context::NodeName(Scope::Variables), None)),
+            ),
+            ("default \\\n    |", Some((entry("default", false), None))),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(at_marker(text), *expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn string_tokens() {
+        let cases: &[(&str, Option<(&str, usize)>)] = &[
+            (r#""value" rest"#, Some(("value", 7))),
+            (r#""say \"hi\"" rest"#, Some((r#"say \"hi\""#, 12))),
+            (r##"#"raw \"# rest"##, Some((r"raw \", 9))),
+            (r###"##"a "# b"## rest"###, Some((r##"a "# b"##, 12))),
+            ("\"\"\"\nline\n\"\"\" rest", Some(("\nline\n", 12))),
+            ("\"open\nnext", Some(("open", 5))),
+            ("\"open", None),
+            ("\"\"\"\nopen", None),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(string_token(text), *expected, "{:?}", text);
+        }
+    }
+}
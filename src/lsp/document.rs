@@ -0,0 +1,388 @@
+use crate::reader::node::{self, NodeType};
+use crate::reader::{self, Format, Scope, TaskFileReadError};
+use crate::tasks::TaskFile;
+use crate::validator::{self, Severity};
+use camino::{Utf8Path, Utf8PathBuf};
+use kdl::{KdlDocument, KdlEntry, KdlNode};
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use miette::SourceSpan;
+use std::fs;
+
+/// Open task file with what was learned from reading it
+pub struct Document {
+    pub path: Utf8PathBuf,
+    pub text: String,
+    /// Parsed KDL, `None` for other formats and for KDL with syntax errors
+    pub kdl: Option<KdlDocument>,
+    /// Last version of the task file that could be read. Kept while the document has errors so
+    /// completion and hover go on working
+    pub task_file: Option<TaskFile>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Something a document defines
+#[derive(Clone, Copy)]
+pub enum Definition<'a> {
+    Default,
+    Task(&'a str),
+    Variable(&'a str),
+}
+
+/// Part of a KDL document under the cursor
+pub enum Located<'a> {
+    NodeName {
+        node: &'a KdlNode,
+        node_type: Option<NodeType>,
+        scope: Scope,
+    },
+    Entry {
+        node_type: Option<NodeType>,
+        entry: &'a KdlEntry,
+        task: Option<&'a str>,
+    },
+}
+
+impl Document {
+    /// Parses a document without reading it as a task file, enough to look up definitions
+    pub fn load(path: &Utf8Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+
+        Some(Self::parse(path.to_path_buf(), text))
+    }
+
+    fn parse(path: Utf8PathBuf, text: String) -> Self {
+        let kdl = match Format::from_path(&path) {
+            Format::Kdl => text.parse().ok(),
+            _ => None,
+        };
+
+        Self {
+            path,
+            text,
+            kdl,
+            task_file: None,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Reads and validates a document, falling back to `previous` when it cannot be read
+    pub fn read(path: Utf8PathBuf, text: String, previous: Option<TaskFile>) -> Self {
+        let mut document = Self::parse(path, text);
+        let dir = document.dir();
+        let format = Format::from_path(&document.path);
+
+        // Imports are resolved against the task file's directory, as when jatr is run next to it
+        match reader::read_format_in(document.text.clone(), format, &dir) {
+            Ok(task_file) => {
+                document.diagnostics = validator::validate(&task_file, &dir)
+                    .into_iter()
+                    .filter_map(|diagnostic| document.validator_diagnostic(&task_file, diagnostic))
+                    .collect();
+                document.task_file = Some(task_file);
+            }
+            Err(e) => {
+                document.diagnostics = document.read_error_diagnostics(&e);
+                document.task_file = previous;
+            }
+        }
+
+        document
+    }
+
+    pub fn dir(&self) -> Utf8PathBuf {
+        match self.path.parent() {
+            Some(dir) if !dir.as_str().is_empty() => dir.to_path_buf(),
+            _ => Utf8PathBuf::from("."),
+        }
+    }
+
+    /// Resolves a path read from the task file, like the source of an imported task
+    pub fn resolve(&self, path: &Utf8Path) -> Utf8PathBuf {
+        self.dir().join(path)
+    }
+
+    fn read_error_diagnostics(&self, e: &TaskFileReadError) -> Vec<Diagnostic> {
+        match e {
+            TaskFileReadError::Syntax(e) => e
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let mut message = diagnostic
+                        .message
+                        .clone()
+                        .or(diagnostic.label.clone())
+                        .unwrap_or_else(|| String::from("invalid KDL"));
+                    if let Some(help) = &diagnostic.help {
+                        message.push_str(&format!(" ({})", help));
+                    }
+
+                    error(self.span_range(diagnostic.span), message)
+                })
+                .collect(),
+            TaskFileReadError::Parser(parser_error, Some(location)) => {
+                let start = line_position(&self.text, location.line - 1, location.column - 1);
+                let end = line_position(&self.text, location.line - 1, usize::MAX);
+                vec![error(Range::new(start, end), parser_error.to_string())]
+            }
+            e => vec![error(Range::default(), e.to_string())],
+        }
+    }
+
+    /// Places a validator diagnostic on the node it is about. Diagnostics about imported tasks
+    /// belong to the file they are imported from
+    fn validator_diagnostic(
+        &self,
+        task_file: &TaskFile,
+        diagnostic: validator::Diagnostic,
+    ) -> Option<Diagnostic> {
+        let location = diagnostic.location.as_str();
+        let quoted = |prefix: &str| {
+            location
+                .strip_prefix(prefix)
+                .and_then(|name| name.strip_prefix('\''))
+                .and_then(|name| name.strip_suffix('\''))
+        };
+
+        let definition = match (location, quoted("task "), quoted("variable ")) {
+            ("default", _, _) => Definition::Default,
+            (_, Some(name), _) => {
+                let task = task_file.tasks.get(name)?;
+                if task.source.is_some() {
+                    return None;
+                }
+                Definition::Task(name)
+            }
+            (_, _, Some(name)) => {
+                let variable = task_file.variables.iter().find(|v| v.name == name)?;
+                if variable.source.is_some() {
+                    return None;
+                }
+                Definition::Variable(name)
+            }
+            _ => return Some(error(Range::default(), diagnostic.message)),
+        };
+
+        let range = self.find(definition).unwrap_or_default();
+        let severity = match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        };
+
+        Some(Diagnostic {
+            severity: Some(severity),
+            ..error(range, diagnostic.message)
+        })
+    }
+
+    /// Range of the name of a definition in this document
+    pub fn find(&self, definition: Definition) -> Option<Range> {
+        let Some(kdl) = &self.kdl else {
+            let key = match definition {
+                Definition::Default => "default",
+                Definition::Task(name) | Definition::Variable(name) => name,
+            };
+            return key_range(&self.text, key);
+        };
+
+        let node = match definition {
+            Definition::Default => kdl.nodes().iter().find(|n| n.name().value() == "default")?,
+            Definition::Task(name) => {
+                children(kdl, NodeType::Tasks).find(|n| n.name().value() == name)?
+            }
+            Definition::Variable(name) => {
+                children(kdl, NodeType::Variables).find(|n| n.name().value() == name)?
+            }
+        };
+
+        Some(self.span_range(node.name().span()))
+    }
+
+    /// Tasks defined in this document with the range of their name
+    pub fn tasks(&self) -> Vec<(&str, Range)> {
+        let Some(kdl) = &self.kdl else {
+            return vec![];
+        };
+
+        children(kdl, NodeType::Tasks)
+            .map(|task| (task.name().value(), self.span_range(task.name().span())))
+            .collect()
+    }
+
+    /// Node name or entry of the parsed KDL at a byte offset
+    pub fn locate(&self, offset: usize) -> Option<Located<'_>> {
+        locate(self.kdl.as_ref()?, Scope::Global, None, offset)
+    }
+
+    /// Word of identifier characters around a byte offset, with its start offset
+    pub fn word_at(&self, offset: usize) -> Option<(&str, usize)> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let start = self.text[..offset]
+            .rfind(|c: char| !is_word(c))
+            .map_or(0, |i| i + 1);
+        let end = self.text[offset..]
+            .find(|c: char| !is_word(c))
+            .map_or(self.text.len(), |i| offset + i);
+
+        match start < end {
+            true => Some((&self.text[start..end], start)),
+            false => None,
+        }
+    }
+
+    /// True when the byte offset lies in a `{{ }}` or `{% %}` tag of the line
+    pub fn in_template(&self, offset: usize) -> bool {
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let before = &self.text[line_start..offset];
+
+        let open = before.rfind("{{").max(before.rfind("{%"));
+        let close = before.rfind("}}").max(before.rfind("%}"));
+        match (open, close) {
+            (Some(open), Some(close)) => open > close,
+            (open, _) => open.is_some(),
+        }
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        let mut line_start = 0;
+        for _ in 0..position.line {
+            match self.text[line_start..].find('\n') {
+                Some(i) => line_start += i + 1,
+                None => return self.text.len(),
+            }
+        }
+
+        let line = &self.text[line_start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character {
+                return line_start + i;
+            }
+            units += c.len_utf16() as u32;
+        }
+
+        line_start + line.len()
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Position::new(
+            before.matches('\n').count() as u32,
+            before[line_start..].encode_utf16().count() as u32,
+        )
+    }
+
+    pub fn span_range(&self, span: SourceSpan) -> Range {
+        Range::new(
+            self.position(span.offset()),
+            self.position(span.offset() + span.len()),
+        )
+    }
+}
+
+fn error(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(String::from("jatr")),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Position of the character at a zero based line and column, clamped to the line
+fn line_position(text: &str, line: usize, column: usize) -> Position {
+    let content = text.lines().nth(line).unwrap_or_default();
+    let character: usize = content.chars().take(column).map(char::len_utf16).sum();
+
+    Position::new(line as u32, character as u32)
+}
+
+/// Children of the top level nodes of the given type, like the tasks in `tasks`
+fn children(kdl: &KdlDocument, parent: NodeType) -> impl Iterator<Item = &KdlNode> {
+    kdl.nodes()
+        .iter()
+        .filter(move |n| node::node_type_in_scope(n.name().value(), &Scope::Global) == Some(parent))
+        .filter_map(|n| n.children())
+        .flat_map(|children| children.nodes())
+}
+
+fn contains(span: SourceSpan, offset: usize) -> bool {
+    span.offset() <= offset && offset <= span.offset() + span.len()
+}
+
+fn locate<'a>(
+    document: &'a KdlDocument,
+    scope: Scope,
+    task: Option<&'a str>,
+    offset: usize,
+) -> Option<Located<'a>> {
+    let node = document
+        .nodes()
+        .iter()
+        .find(|node| contains(node.span(), offset))?;
+    let node_type = node::node_type_in_scope(node.name().value(), &scope);
+    let task = match scope {
+        Scope::Tasks => Some(node.name().value()),
+        _ => task,
+    };
+
+    if contains(node.name().span(), offset) {
+        return Some(Located::NodeName {
+            node,
+            node_type,
+            scope,
+        });
+    }
+
+    if let Some(entry) = node.entries().iter().find(|e| contains(e.span(), offset)) {
+        return Some(Located::Entry {
+            node_type,
+            entry,
+            task,
+        });
+    }
+
+    let child_scope = node_type?.spec().children.clone()?;
+    locate(node.children()?, child_scope, task, offset)
+}
+
+/// Range of `name` where it is defined as a key in YAML, TOML or JSON, a Makefile target or
+/// a just recipe
+fn key_range(text: &str, name: &str) -> Option<Range> {
+    for (number, line) in text.split('\n').enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        let unquoted = line[indent..].trim_start_matches('"');
+        let column = line.len() - unquoted.len();
+
+        let is_key = unquoted.strip_prefix(name).is_some_and(|rest| {
+            let rest = rest.trim_start_matches('"');
+            rest.trim_start().starts_with([':', '='])
+                || rest.starts_with(' ') && rest.contains(':') && !line.starts_with('#')
+        });
+        let table = line.find(&format!(".{}]", name)).map(|i| i + 1);
+
+        let start = match (is_key, table) {
+            (true, _) => Some(column),
+            (false, Some(start)) => Some(start),
+            _ => None,
+        };
+
+        if let Some(start) = start {
+            let character = |i: usize| line[..i].encode_utf16().count() as u32;
+            return Some(Range::new(
+                Position::new(number as u32, character(start)),
+                Position::new(number as u32, character(start + name.len())),
+            ));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,710 @@
+//! Language server for task files, speaking LSP over stdio.
+//!
+//! Every open task file gets diagnostics from reading and validating it. KDL task files also
+//! get completion of node names, task names and template variables, go-to-definition and hover
+//! for tasks and variables across imports, and code lenses running tasks.
+
+mod cursor;
+mod document;
+
+use crate::reader::node::{self, NodeType};
+use crate::reader::{Format, Scope};
+use crate::runner::redact::MASK;
+use crate::runner::templating::{Templating, BUILTINS};
+use crate::tasks::{Action, Task, TaskFile, Value, Variable, VariableValue};
+use crate::writer::quote;
+use camino::{Utf8Path, Utf8PathBuf};
+use cursor::Context;
+use document::{Definition, Document, Located};
+use kdl::KdlValue;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{self, Notification as _};
+use lsp_types::request::{self, Request as _};
+use lsp_types::{
+    CodeLens, CodeLensOptions, CodeLensParams, Command, CompletionItem, CompletionItemKind,
+    CompletionOptions, CompletionParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, Documentation, ExecuteCommandOptions, ExecuteCommandParams,
+    GotoDefinitionParams, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    LogMessageParams, MarkupContent, MarkupKind, MessageType, OneOf, PublishDiagnosticsParams,
+    Range, ServerCapabilities, ShowMessageParams, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Uri,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::process;
+use std::thread;
+use url::Url;
+
+/// Command of the code lenses, runs the task given as second argument from the task file
+/// given as first argument
+const RUN_TASK: &str = "jatr.runTask";
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Uri, Document>,
+}
+
+/// Serves the language server protocol on stdin and stdout until the client exits
+pub fn serve() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![String::from("\""), String::from("{")]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![String::from(RUN_TASK)],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.run()?;
+
+    drop(server);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+impl Server {
+    fn run(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&mut self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            request::Completion::METHOD => params(request).map(|p| json(self.completion(p))),
+            request::HoverRequest::METHOD => params(request).map(|p| json(self.hover(p))),
+            request::GotoDefinition::METHOD => params(request).map(|p| json(self.definition(p))),
+            request::CodeLensRequest::METHOD => params(request).map(|p| json(self.code_lenses(p))),
+            request::ExecuteCommand::METHOD => params(request).map(|p| json(self.execute(p))),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request '{}'", method),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => Response::new_err(id, ErrorCode::InvalidParams as i32, message),
+        }
+    }
+
+    fn notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match notification.method.as_str() {
+            notification::DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.update(params.text_document.uri, params.text_document.text)?;
+            }
+            notification::DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.update(params.text_document.uri, change.text)?;
+                }
+            }
+            notification::DidSaveTextDocument::METHOD => {
+                // Other open documents may import the saved one
+                let open: Vec<(Uri, String)> = self
+                    .documents
+                    .iter()
+                    .map(|(uri, document)| (uri.clone(), document.text.clone()))
+                    .collect();
+                for (uri, text) in open {
+                    self.update(uri, text)?;
+                }
+            }
+            notification::DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, uri: Uri, text: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(path) = uri_to_path(&uri) else {
+            return Ok(());
+        };
+
+        let previous = self.documents.remove(&uri).and_then(|d| d.task_file);
+        let document = Document::read(path, text, previous);
+        let diagnostics = document.diagnostics.clone();
+        self.documents.insert(uri.clone(), document);
+
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(
+        &self,
+        uri: Uri,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification::new(
+            String::from(notification::PublishDiagnostics::METHOD),
+            params,
+        );
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+
+        Ok(())
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<Vec<CompletionItem>> {
+        let position = params.text_document_position;
+        let document = self.documents.get(&position.text_document.uri)?;
+        if Format::from_path(&document.path) != Format::Kdl {
+            return None;
+        }
+
+        let offset = document.offset(position.position);
+        let cursor = cursor::cursor(&document.text, offset)?;
+        let task_file = document.task_file.as_ref();
+
+        let items = match cursor.context {
+            Context::NodeName(scope) => node_name_items(&scope),
+            Context::Entry { node, quoted } => match node.as_str() {
+                "task" | "default" => task_items(task_file?, quoted),
+                _ => vec![],
+            },
+            Context::Template => variable_items(task_file?, cursor.task.as_deref()),
+        };
+
+        Some(items)
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let task_file = document.task_file.as_ref()?;
+        let offset = document.offset(position.position);
+
+        let markdown = match document.locate(offset)? {
+            Located::NodeName {
+                node,
+                scope: Scope::Tasks,
+                ..
+            } => task_hover(task_file, node.name().value())?,
+            Located::NodeName {
+                node,
+                scope: Scope::Variables,
+                ..
+            } => variable_hover(task_file, None, node.name().value())?,
+            Located::NodeName {
+                node_type: Some(node_type),
+                node,
+                ..
+            } => format!("**{}**\n\n{}", node.name().value(), node_type.spec().doc),
+            Located::Entry { task, .. } if document.in_template(offset) => {
+                let (word, _) = document.word_at(offset)?;
+                variable_hover(task_file, task, word)?
+            }
+            Located::Entry {
+                node_type: Some(NodeType::ActionTask | NodeType::Default),
+                entry,
+                ..
+            } if entry.name().is_none() => task_hover(task_file, entry.value().as_string()?)?,
+            Located::Entry {
+                node_type: Some(node_type),
+                entry,
+                ..
+            } => {
+                let spec = node_type.spec();
+                match entry.name() {
+                    Some(name) => {
+                        let property = spec.property(name.value())?;
+                        format!("**{}**\n\n{}", property.name, property.doc)
+                    }
+                    None => {
+                        let argument = spec.arguments.first()?;
+                        format!("**{}**\n\n{}", argument.name, argument.doc)
+                    }
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: markdown,
+            }),
+            range: None,
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<Location> {
+        let position = params.text_document_position_params;
+        let document = self.documents.get(&position.text_document.uri)?;
+        let task_file = document.task_file.as_ref()?;
+        let offset = document.offset(position.position);
+
+        let Located::Entry {
+            node_type,
+            entry,
+            task,
+            ..
+        } = document.locate(offset)?
+        else {
+            return None;
+        };
+
+        if document.in_template(offset) {
+            let (word, _) = document.word_at(offset)?;
+            let variable = find_variable(task_file, task, word)?;
+            let local = task.filter(|_| !is_global(task_file, variable));
+
+            return match (&variable.source, local) {
+                (Some(source), _) => {
+                    let path = document.resolve(source);
+                    let range = Document::load(&path)?
+                        .find(Definition::Variable(&variable.name))
+                        .unwrap_or_default();
+                    location(&path, range)
+                }
+                // Variables of a task have no definition lookup of their own, the task is close
+                (None, Some(task)) => {
+                    location(&document.path, document.find(Definition::Task(task))?)
+                }
+                (None, None) => location(
+                    &document.path,
+                    document.find(Definition::Variable(&variable.name))?,
+                ),
+            };
+        }
+
+        let KdlValue::String(value) = entry.value() else {
+            return None;
+        };
+
+        match node_type? {
+            NodeType::Import if entry.name().is_none() => {
+                location(&document.resolve(Utf8Path::new(value)), Range::default())
+            }
+            NodeType::ActionTask | NodeType::Default if entry.name().is_none() => {
+                let (key, task) = find_task(task_file, value)?;
+                match &task.source {
+                    Some(source) => {
+                        let path = document.resolve(source);
+                        let range = Document::load(&path)?
+                            .find(Definition::Task(&task.name))
+                            .unwrap_or_default();
+                        location(&path, range)
+                    }
+                    None => location(&document.path, document.find(Definition::Task(key))?),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn code_lenses(&self, params: CodeLensParams) -> Option<Vec<CodeLens>> {
+        let document = self.documents.get(&params.text_document.uri)?;
+
+        let lenses = document
+            .tasks()
+            .into_iter()
+            .map(|(name, range)| CodeLens {
+                range,
+                command: Some(Command {
+                    title: format!("▶ Run {}", name),
+                    command: String::from(RUN_TASK),
+                    arguments: Some(vec![Json::from(document.path.as_str()), Json::from(name)]),
+                }),
+                data: None,
+            })
+            .collect();
+
+        Some(lenses)
+    }
+
+    /// Runs a task in the background, reporting its output to the client's log and the
+    /// outcome as a message
+    fn execute(&self, params: ExecuteCommandParams) -> Option<Json> {
+        let [file, task] = params.arguments.as_slice() else {
+            return None;
+        };
+        let (file, task) = (String::from(file.as_str()?), String::from(task.as_str()?));
+        if params.command != RUN_TASK {
+            return None;
+        }
+
+        let sender = self.connection.sender.clone();
+        thread::spawn(move || {
+            let path = Utf8PathBuf::from(&file);
+            let dir = path.parent().map(Utf8Path::to_path_buf).unwrap_or_default();
+            let output = std::env::current_exe().and_then(|jatr| {
+                process::Command::new(jatr)
+                    .arg("-f")
+                    .arg(&file)
+                    .arg(&task)
+                    .current_dir(dir)
+                    .output()
+            });
+
+            let (message_type, message) = match output {
+                Ok(output) => {
+                    let log = LogMessageParams {
+                        typ: MessageType::LOG,
+                        message: strip_colors(&format!(
+                            "{}{}",
+                            String::from_utf8_lossy(&output.stdout),
+                            String::from_utf8_lossy(&output.stderr)
+                        )),
+                    };
+                    let log =
+                        Notification::new(String::from(notification::LogMessage::METHOD), log);
+                    let _ = sender.send(Message::Notification(log));
+
+                    match output.status.code() {
+                        Some(0) => (MessageType::INFO, format!("Task '{}' succeeded", task)),
+                        Some(code) => (
+                            MessageType::ERROR,
+                            format!("Task '{}' failed with exit code {}", task, code),
+                        ),
+                        None => (MessageType::ERROR, format!("Task '{}' was killed", task)),
+                    }
+                }
+                Err(e) => (
+                    MessageType::ERROR,
+                    format!("Could not run task '{}': {}", task, e),
+                ),
+            };
+
+            let message = ShowMessageParams {
+                typ: message_type,
+                message,
+            };
+            let message =
+                Notification::new(String::from(notification::ShowMessage::METHOD), message);
+            let _ = sender.send(Message::Notification(message));
+        });
+
+        Some(Json::Null)
+    }
+}
+
+fn params<P: DeserializeOwned>(request: Request) -> Result<P, String> {
+    serde_json::from_value(request.params).map_err(|e| e.to_string())
+}
+
+fn json(value: impl serde::Serialize) -> Json {
+    serde_json::to_value(value).unwrap_or(Json::Null)
+}
+
+fn location(path: &Utf8Path, range: Range) -> Option<Location> {
+    Some(Location {
+        uri: path_to_uri(path)?,
+        range,
+    })
+}
+
+fn node_name_items(scope: &Scope) -> Vec<CompletionItem> {
+    node::names_in_scope(scope)
+        .into_iter()
+        .filter_map(|name| {
+            let node_type = node::node_type_in_scope(name, scope)?;
+            Some(CompletionItem {
+                label: String::from(name),
+                kind: Some(CompletionItemKind::KEYWORD),
+                documentation: Some(markdown(node_type.spec().doc)),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn task_items(task_file: &TaskFile, quoted: bool) -> Vec<CompletionItem> {
+    let mut items = vec![];
+    for (key, task) in &task_file.tasks {
+        for name in [key].into_iter().chain(&task.aliases) {
+            items.push(CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: task.description.clone(),
+                insert_text: (!quoted).then(|| quote(name)),
+                ..Default::default()
+            });
+        }
+    }
+
+    items
+}
+
+fn variable_items(task_file: &TaskFile, task: Option<&str>) -> Vec<CompletionItem> {
+    let task_variables = task
+        .and_then(|task| task_file.tasks.get(task))
+        .map(|task| task.variables.as_slice())
+        .unwrap_or_default();
+
+    let mut items: Vec<CompletionItem> = task_file
+        .variables
+        .iter()
+        .chain(task_variables)
+        .map(|variable| CompletionItem {
+            label: variable.name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(describe_variable(variable)),
+            ..Default::default()
+        })
+        .collect();
+
+    for (name, doc) in BUILTINS {
+        if items.iter().all(|item| item.label != *name) {
+            items.push(CompletionItem {
+                label: String::from(*name),
+                kind: Some(CompletionItemKind::CONSTANT),
+                detail: Some(String::from("built-in")),
+                documentation: Some(markdown(doc)),
+                ..Default::default()
+            });
+        }
+    }
+
+    items
+}
+
+fn markdown(text: &str) -> Documentation {
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: String::from(text),
+    })
+}
+
+fn find_task<'a>(task_file: &'a TaskFile, name: &str) -> Option<(&'a str, &'a Task)> {
+    task_file
+        .tasks
+        .iter()
+        .find(|(key, task)| *key == name || task.aliases.iter().any(|a| a == name))
+        .map(|(key, task)| (key.as_str(), task))
+}
+
+/// Variable visible in a task, task variables shadowing global ones
+fn find_variable<'a>(
+    task_file: &'a TaskFile,
+    task: Option<&str>,
+    name: &str,
+) -> Option<&'a Variable> {
+    let task_variables = task.and_then(|task| task_file.tasks.get(task)).into_iter();
+
+    task_variables
+        .flat_map(|task| task.variables.iter().rev())
+        .chain(task_file.variables.iter().rev())
+        .find(|variable| variable.name == name)
+}
+
+fn is_global(task_file: &TaskFile, variable: &Variable) -> bool {
+    task_file
+        .variables
+        .iter()
+        .any(|v| std::ptr::eq(v, variable))
+}
+
+fn task_hover(task_file: &TaskFile, name: &str) -> Option<String> {
+    let (key, task) = find_task(task_file, name)?;
+    let mut markdown = format!("**{}**", key);
+
+    if let Some(description) = &task.description {
+        markdown.push_str(&format!("\n\n{}", description));
+    }
+    if !task.aliases.is_empty() {
+        let aliases: Vec<String> = task.aliases.iter().map(|a| format!("`{}`", a)).collect();
+        markdown.push_str(&format!("\n\nAliases: {}", aliases.join(", ")));
+    }
+    if let Some(source) = &task.source {
+        markdown.push_str(&format!("\n\nImported from `{}`", source));
+    }
+
+    let resolved = resolve_static(task_file.variables.iter().chain(&task.variables));
+    if !resolved.is_empty() {
+        markdown.push_str("\n\nVariables:");
+        for (variable, value) in resolved {
+            markdown.push_str(&format!("\n- `{}` {}", variable.name, value));
+        }
+    }
+
+    Some(markdown)
+}
+
+fn variable_hover(task_file: &TaskFile, task: Option<&str>, name: &str) -> Option<String> {
+    let Some(variable) = find_variable(task_file, task, name) else {
+        let (name, doc) = BUILTINS.iter().find(|(builtin, _)| *builtin == name)?;
+        return Some(format!("**{}** (built-in)\n\n{}", name, doc));
+    };
+    let visible = task_file.variables.iter().chain(
+        task.and_then(|t| task_file.tasks.get(t))
+            .into_iter()
+            .flat_map(|t| &t.variables),
+    );
+
+    let (_, value) = resolve_static(visible)
+        .into_iter()
+        .rfind(|(v, _)| std::ptr::eq(*v, variable))?;
+
+    let mut markdown = format!("**{}** {}", variable.name, value);
+    if let Some(source) = &variable.source {
+        markdown.push_str(&format!("\n\nImported from `{}`", source));
+    }
+
+    Some(markdown)
+}
+
+/// Describes each variable's value, rendering static templates with the static values
+/// before them. Command variables are described by their command since running it could
+/// have side effects. Secret values are masked
+fn resolve_static<'a>(
+    variables: impl Iterator<Item = &'a Variable>,
+) -> Vec<(&'a Variable, String)> {
+    let mut templating = Templating::default();
+    let mut resolved = vec![];
+
+    for variable in variables {
+        let value = match &variable.value {
+            VariableValue::Static(_) if variable.secret => {
+                templating.add_variable(&variable.name, Value::String(String::from(MASK)));
+                format!("= `{}`", MASK)
+            }
+            VariableValue::Static(Value::String(template)) => {
+                let rendered = templating
+                    .process(template)
+                    .unwrap_or_else(|_| template.clone());
+                let value = Value::String(rendered);
+                let description = format!("= `{}`", display_value(&value));
+                templating.add_variable(&variable.name, value);
+                description
+            }
+            VariableValue::Static(value) => {
+                templating.add_variable(&variable.name, value.clone());
+                format!("= `{}`", display_value(value))
+            }
+            _ => describe_variable(variable),
+        };
+
+        resolved.push((variable, with_type(variable, value)));
+    }
+
+    resolved
+}
+
+fn describe_variable(variable: &Variable) -> String {
+    let description = match &variable.value {
+        VariableValue::Static(_) if variable.secret => format!("= {}", MASK),
+        VariableValue::Static(value) => format!("= {}", display_value(value)),
+        VariableValue::Action(Action::Command(cmd)) => format!("= output of `{}`", cmd.command),
+        VariableValue::Action(_) => String::new(),
+        VariableValue::File(path) => format!("= contents of `{}`", path),
+        VariableValue::Env(name) => format!("= environment variable `{}`", name),
+        VariableValue::Prompt(prompt) => format!("= answer to `{}`", prompt.message),
+    };
+
+    with_type(variable, description)
+}
+
+/// Prefixes a description with the declared type of the variable
+fn with_type(variable: &Variable, description: String) -> String {
+    match variable.constraints.value_type {
+        Some(value_type) => format!("{} {}", value_type.name(), description),
+        None => description,
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => quote(s),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::from("null"),
+        Value::List(items) => {
+            let items: Vec<String> = items.iter().map(display_value).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("{}: {}", quote(key), display_value(value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+/// Removes the terminal color codes jatr prints, clients show log messages as plain text
+fn strip_colors(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        plain.push_str(&rest[..start]);
+        let code = &rest[start + 2..];
+        rest = match code.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(end) => &code[end + 1..],
+            None => "",
+        };
+    }
+    plain.push_str(rest);
+
+    plain
+}
+
+fn uri_to_path(uri: &Uri) -> Option<Utf8PathBuf> {
+    let path = Url::parse(uri.as_str()).ok()?.to_file_path().ok()?;
+
+    Utf8PathBuf::from_path_buf(path).ok()
+}
+
+fn path_to_uri(path: &Utf8Path) -> Option<Uri> {
+    let path = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => Utf8PathBuf::from_path_buf(std::env::current_dir().ok()?.join(path)).ok()?,
+    };
+
+    Url::from_file_path(path).ok()?.as_str().parse().ok()
+}
@@ -1,19 +1,16 @@
-mod reader;
-mod runner;
-mod tasks;
-mod utils;
+mod commands;
+mod picker;
 
-use crate::tasks::{Task, TaskFile};
+use jatr::reader;
+use jatr::{Runner, RunnerEnvironment, RunnerErrorData, RunnerResult, Task, TaskFile};
 use camino::Utf8Path;
 use clap::{ArgAction};
-use log::{error, LevelFilter};
-use runner::environment::RunnerEnvironment;
-use runner::{Runner, RunnerResult};
+use log::{error, warn, LevelFilter};
 use std::env::args_os;
 use std::{env, io};
-use color_print::cstr;
+use color_print::{cformat, cstr};
 
-const FILE: &str = "tasks.kdl";
+const FILES: &[&str] = &["tasks.kdl", "tasks.yaml", "tasks.yml", "tasks.toml", "tasks.json"];
 
 fn get_file_from_args() -> Option<String> {
     let mut is_file = false;
@@ -49,8 +46,6 @@ fn setup_logging(verbose: bool) {
 fn bootstrap_cmd() -> clap::Command {
     clap::Command::new("jatr")
         .bin_name("jatr")
-        .arg_required_else_help(true)
-        .subcommand_required(true)
         .args(vec![
             clap::arg!(verbose: -v --verbose "Enables verbose output")
                 .global(true)
@@ -58,32 +53,89 @@ fn bootstrap_cmd() -> clap::Command {
             clap::arg!(file: -f --file "Specify task file")
                 .global(true)
                 .action(ArgAction::Set),
+            clap::arg!(list: -l --list "Lists available tasks").action(ArgAction::SetTrue),
+            clap::arg!(non_interactive: --"non-interactive" "Fails instead of asking questions")
+                .global(true)
+                .action(ArgAction::SetTrue),
+            clap::arg!(var: --var <ASSIGNMENT> "Overrides a variable, as NAME=VALUE")
+                .global(true)
+                .action(ArgAction::Append),
         ])
+}
+
+fn print_task_list(task_file: &TaskFile) {
+    let mut tasks: Vec<(&String, &Task)> =
+        task_file.tasks.iter().filter(|(_, t)| !t.private).collect();
+    tasks.sort_by(|a, b| a.0.cmp(b.0));
+
+    let width = tasks.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let default = task_file
+        .default
+        .as_deref()
+        .and_then(|name| task_file.find_task(name));
+
+    println!(cstr!("<bold><underline>Tasks:</underline></bold>"));
+    for (name, task) in tasks {
+        let mut line = format!(
+            "  {}  {}",
+            cformat!("<bold>{:width$}</bold>", name),
+            task.description.as_deref().unwrap_or("")
+        );
+
+        if !task.aliases.is_empty() {
+            line.push_str(&cformat!(" <bright-black>[aliases: {}]</>", task.aliases.join(", ")));
+        }
+
+        if default.is_some_and(|d| std::ptr::eq(d, task)) {
+            line.push_str(cstr!(" <green>(default)</>"));
+        }
 
+        println!("{}", line.trim_end());
+    }
 }
 
 fn main() {
-    let file = get_file_from_args().unwrap_or_else(|| String::from(FILE));
-    let path = Utf8Path::from_path(env::current_dir().unwrap().as_path())
+    let current_dir = Utf8Path::from_path(env::current_dir().unwrap().as_path())
         .unwrap()
-        .join(Utf8Path::new(&file));
+        .to_path_buf();
+    let path = match get_file_from_args() {
+        Some(file) => current_dir.join(file),
+        None => FILES
+            .iter()
+            .map(|file| current_dir.join(file))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| current_dir.join(FILES[0])),
+    };
 
     setup_logging(get_verbose_from_args());
 
-    let task_file = match reader::open_and_read(&path) {
-        Ok(t) => t,
-        Err(e) => {
-            error!("Error reading file: {}. Error: {:#?}", path, e);
-            std::process::exit(1);
-        }
-    };
+    let read_result = reader::open_and_read(&path);
+
+    let mut cmd = bootstrap_cmd()
+        .subcommands(commands::builtin_commands().into_iter().map(|c| c.hide(true)))
+        .after_help(commands::help());
+
+    match &read_result {
+        Ok(task_file) => {
+            for (key, name) in commands::shadowed(task_file) {
+                warn!("{}", commands::shadowed_message(key, name));
+            }
 
-    let mut cmd = bootstrap_cmd();
-    for (name, task) in &task_file.tasks {
-        let about = task.description.clone().unwrap_or_else(String::new);
-        let subc = clap::command!(name).about(about);
+            for (name, task) in task_file.tasks.iter().filter(|(_, t)| !t.private) {
+                if commands::is_builtin(name) {
+                    continue;
+                }
 
-        cmd = cmd.subcommand(subc);
+                let about = task.description.clone().unwrap_or_default();
+                let aliases = task.aliases.iter().filter(|a| !commands::is_builtin(a));
+                let subc = clap::command!(name)
+                    .about(about)
+                    .visible_aliases(aliases.cloned().collect::<Vec<_>>());
+
+                cmd = cmd.subcommand(commands::task_args(subc));
+            }
+        }
+        Err(_) => cmd = cmd.allow_external_subcommands(true),
     }
 
     cmd = cmd.help_template(color_print::cstr!(
@@ -97,15 +149,56 @@ r#"{usage-heading} {usage}
 {after-help}
 "#));
 
-    let global_matches = cmd.get_matches();
+    let global_matches = cmd.get_matches_mut();
+
+    if let Some((name, matches)) = global_matches.subcommand() {
+        if commands::is_builtin(name) && name != "run" {
+            std::process::exit(commands::run(name, matches, &path, &read_result));
+        }
+    }
+
+    let task_file = match read_result {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Error reading file {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    if global_matches.get_flag("list") {
+        print_task_list(&task_file);
+        std::process::exit(0);
+    }
 
-    let (name, matches) = match global_matches.subcommand() {
-        Some((name, matches)) => (name, matches),
-        None => unreachable!("Subcommand not found"),
+    let can_pick = picker::is_available() && !global_matches.get_flag("non_interactive");
+    let task = match (global_matches.subcommand(), &task_file.default) {
+        (Some(("run", matches)), _) => {
+            find_task_or_exit(&task_file, matches.get_one::<String>("task").unwrap())
+        }
+        (Some((name, _)), _) => find_task_or_exit(&task_file, name),
+        (None, Some(default)) => find_task_or_exit(&task_file, default),
+        (None, None) if can_pick => match picker::pick_task(&task_file) {
+            Ok(Some(task)) => task,
+            Ok(None) => std::process::exit(0),
+            Err(e) => {
+                error!("Error selecting task: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => {
+            let _ = cmd.print_help();
+            std::process::exit(2);
+        }
     };
 
-    let Some(task) = task_file.tasks.get(name) else {
-        unreachable!("Task not found")
+    if task.private {
+        error!("Task '{}' is private and cannot be run directly", task.name);
+        std::process::exit(1);
+    }
+
+    let matches = match global_matches.subcommand() {
+        Some((_, matches)) => matches,
+        None => &global_matches,
     };
 
     match run_task(
@@ -116,40 +209,111 @@ r#"{usage-heading} {usage}
     ) {
         Ok(exit_code) => std::process::exit(exit_code),
         Err(e) => {
-            error!("Unexpected error running task: {:?}", e);
+            error!("Unexpected error running task: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn find_task_or_exit<'a>(task_file: &'a TaskFile, name: &str) -> &'a Task {
+    match task_file.find_task(name) {
+        Some(task) => task,
+        None => {
+            error!("Task '{}' not found", name);
             std::process::exit(1);
         }
     }
 }
 
+/// Variable overrides given with `--var` and as positional `NAME=VALUE` arguments, in the
+/// order they apply
+fn variable_overrides(
+    args: &clap::ArgMatches,
+    task_file: &TaskFile,
+) -> Result<Vec<(String, String)>, String> {
+    let values = |id: &str| -> Vec<String> {
+        args.try_get_many::<String>(id)
+            .ok()
+            .flatten()
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+
+    let declared: Vec<&str> = task_file
+        .variables
+        .iter()
+        .chain(task_file.tasks.values().flat_map(|t| t.variables.iter()))
+        .map(|v| v.name.as_str())
+        .collect();
+
+    let mut overrides = vec![];
+    for assignment in values("var").into_iter().chain(values("assignments")) {
+        let Some((name, value)) = assignment.split_once('=') else {
+            return Err(format!("Expected NAME=VALUE, got '{}'", assignment));
+        };
+
+        if !declared.contains(&name) {
+            return Err(format!("Variable '{}' is not defined in the task file", name));
+        }
+
+        overrides.push((String::from(name), String::from(value)));
+    }
+
+    Ok(overrides)
+}
+
 fn run_task<'a>(
     task: &'a Task,
     work_dir: &str,
-    _args: &clap::ArgMatches,
+    args: &clap::ArgMatches,
     tasks: &'a TaskFile,
 ) -> Result<i32, io::Error> {
     let mut env = RunnerEnvironment::default();
     env.work_dir(work_dir).unwrap();
+    env.task_file_dir(&env.get_work_dir());
+    env.interactive(!args.get_flag("non_interactive"));
+
+    if let Ok(Some(args)) = args.try_get_many::<String>("args") {
+        env.args(args.cloned().collect());
+    }
+
+    match variable_overrides(args, tasks) {
+        Ok(overrides) => {
+            for (name, value) in overrides {
+                env.override_variable(&name, &value);
+            }
+        }
+        Err(e) => {
+            error!("{}", e);
+            return Ok(2);
+        }
+    }
 
     let mut runner = Runner::for_taskfile(tasks, env);
 
     match runner.run(task) {
         Ok(RunnerResult::Success) => {
             println!(cstr!("<green>Success</>"));
-            return Ok(0);
+            Ok(0)
         }
         Ok(RunnerResult::Skipped) => {
             println!(cstr!("<yellow>Skipped</>"));
-            return Ok(0);
+            Ok(0)
         }
         Ok(RunnerResult::Failure) => {
             println!(cstr!("<red>Failure</>"));
-            return Ok(1);
-        }
-        Err(e) => {
-            error!("Error running tasks: {:?}", e);
+            Ok(1)
         }
+        Err(e) => match e.data() {
+            // The message was printed by the fail action
+            RunnerErrorData::Failed { code } => {
+                println!(cstr!("<red>Failure</>"));
+                Ok(*code)
+            }
+            _ => {
+                error!("Error running tasks: {}", e);
+                Ok(1)
+            }
+        },
     }
-
-    Ok(0)
 }
@@ -0,0 +1,88 @@
+use jatr::tasks::{Action, Task, TaskFile};
+use inquire::{InquireError, Select};
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+const PREVIEW_WIDTH: usize = 60;
+
+struct TaskOption<'a> {
+    name: &'a str,
+    task: &'a Task,
+    width: usize,
+}
+
+impl fmt::Display for TaskOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:width$}", self.name, width = self.width)?;
+
+        if let Some(description) = &self.task.description {
+            write!(f, "  {}", description)?;
+        }
+
+        let preview = preview_actions(self.task);
+        if !preview.is_empty() {
+            write!(f, "  › {}", preview)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true when both stdin and stdout are attached to a terminal
+pub fn is_available() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Interactively selects one of the public tasks. Returns `None` when the user cancels the prompt
+pub fn pick_task(task_file: &TaskFile) -> Result<Option<&Task>, InquireError> {
+    let mut tasks: Vec<(&String, &Task)> =
+        task_file.tasks.iter().filter(|(_, t)| !t.private).collect();
+    tasks.sort_by(|a, b| a.0.cmp(b.0));
+
+    let width = tasks.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let options = tasks
+        .into_iter()
+        .map(|(name, task)| TaskOption { name, task, width })
+        .collect();
+
+    let selected = Select::new("Run task:", options)
+        .with_help_message("↑↓ to move, type to filter, enter to run, esc to cancel")
+        .with_formatter(&|option| String::from(option.value.name))
+        .with_page_size(15)
+        .prompt_skippable()?;
+
+    Ok(selected.map(|option| option.task))
+}
+
+fn preview_actions(task: &Task) -> String {
+    let preview = task
+        .actions
+        .iter()
+        .map(preview_action)
+        .collect::<Vec<String>>()
+        .join(" · ")
+        .replace('\n', " ");
+
+    match preview.char_indices().nth(PREVIEW_WIDTH) {
+        Some((index, _)) => format!("{}…", &preview[..index]),
+        None => preview,
+    }
+}
+
+fn preview_action(action: &Action) -> String {
+    match action {
+        Action::Noop => String::from("noop"),
+        Action::Command(cmd) => format!("cmd '{}'", cmd.command),
+        Action::If(cmd) => format!("if '{}'", cmd.command),
+        Action::Task(call) => format!("task '{}'", call.name),
+        Action::Cd(path) => format!("cd '{}'", path),
+        Action::Confirm(message) => format!("confirm '{}'", message),
+        Action::Script(script) => match &script.interpreter {
+            Some(interpreter) => format!("script '{}'", interpreter),
+            None => String::from("script"),
+        },
+        Action::Echo(message) => format!("echo '{}'", message.text),
+        Action::Fail(failure) => format!("fail '{}'", failure.message),
+        Action::File(action) => format!("{} '{}'", action.name(), action.templates()[0]),
+    }
+}
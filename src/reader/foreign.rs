@@ -0,0 +1,264 @@
+//! Synthesises tasks from the build definitions of other tools. Every task runs through the
+//! original tool, so its own behaviour like environment setup and up-to-date checks is kept
+
+use super::TaskFileReadError;
+use crate::tasks::{Action, ActionCommand, ImportFormat, Task, TaskCall, TaskFile};
+use crate::utils::shell_quote;
+use camino::Utf8Path;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::fs;
+
+#[derive(Deserialize)]
+struct PackageJson {
+    #[serde(default)]
+    scripts: IndexMap<String, String>,
+}
+
+#[derive(Default)]
+struct MakeTarget {
+    prerequisites: Vec<String>,
+    description: Option<String>,
+}
+
+pub fn read(path: &Utf8Path, format: ImportFormat) -> Result<TaskFile, TaskFileReadError> {
+    let content = fs::read_to_string(path)?;
+
+    let tasks = match format {
+        ImportFormat::Npm | ImportFormat::Yarn | ImportFormat::Pnpm => {
+            package_scripts(&content, path, format)?
+        }
+        ImportFormat::Make => makefile_targets(&content, path),
+        ImportFormat::Just => justfile_recipes(&content, path),
+    };
+
+    let mut task_file = TaskFile::default();
+    for task in tasks {
+        task_file.tasks.insert(task.name.clone(), task);
+    }
+
+    Ok(task_file)
+}
+
+fn command(command: String) -> Action {
+    Action::Command(ActionCommand {
+        command,
+        shell: None,
+        tty: true,
+    })
+}
+
+fn directory(path: &Utf8Path) -> &str {
+    match path.parent().map(|dir| dir.as_str()) {
+        Some("") | None => ".",
+        Some(dir) => dir,
+    }
+}
+
+/// One task per entry of `scripts`, described by the script itself
+fn package_scripts(
+    content: &str,
+    path: &Utf8Path,
+    format: ImportFormat,
+) -> Result<Vec<Task>, TaskFileReadError> {
+    let package: PackageJson = serde_json::from_str(content)
+        .map_err(|e| TaskFileReadError::Structured(format!("Invalid package.json: {}", e)))?;
+
+    let dir = shell_quote(directory(path));
+    let run = match format {
+        ImportFormat::Yarn => format!("yarn --cwd {} run", dir),
+        ImportFormat::Pnpm => format!("pnpm --dir {} run", dir),
+        _ => format!("npm --prefix {} run", dir),
+    };
+
+    Ok(package
+        .scripts
+        .into_iter()
+        .map(|(name, script)| Task {
+            actions: vec![command(format!("{} {}", run, shell_quote(&name)))],
+            description: Some(script),
+            private: name.starts_with('_'),
+            name,
+            ..Default::default()
+        })
+        .collect())
+}
+
+/// One task per explicit target. Prerequisites that are targets themselves become `task`
+/// actions, and make is told to consider them up to date so they are not remade
+fn makefile_targets(content: &str, path: &Utf8Path) -> Vec<Task> {
+    let mut targets: IndexMap<String, MakeTarget> = IndexMap::new();
+    let mut in_define = false;
+
+    for line in content.replace("\\\n", " ").lines() {
+        let trimmed = line.trim();
+        if in_define {
+            in_define = trimmed != "endef";
+            continue;
+        }
+        if trimmed == "define" || trimmed.starts_with("define ") {
+            in_define = true;
+            continue;
+        }
+        if line.starts_with('\t') || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let (rule, description) = match line.split_once("##") {
+            Some((rule, description)) => (rule, Some(description.trim())),
+            None => (line, None),
+        };
+        let rule = rule.split('#').next().unwrap_or_default();
+
+        let Some((names, rest)) = rule.split_once(':') else {
+            continue;
+        };
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+        if names.contains('=') || rest.starts_with('=') || rest.contains('=') {
+            continue;
+        }
+
+        let prerequisites: Vec<String> = rest
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter(|word| *word != "|" && !word.contains('$'))
+            .map(String::from)
+            .collect();
+
+        let names = names
+            .split_whitespace()
+            .filter(|name| !name.starts_with('.') && !name.contains(['%', '$']));
+        for name in names {
+            let target = targets.entry(String::from(name)).or_default();
+            target.prerequisites.extend(prerequisites.iter().cloned());
+            if target.description.is_none() {
+                target.description = description.filter(|d| !d.is_empty()).map(String::from);
+            }
+        }
+    }
+
+    let dir = shell_quote(directory(path));
+    let file = shell_quote(path.file_name().unwrap_or("Makefile"));
+
+    targets
+        .iter()
+        .map(|(name, target)| {
+            let dependencies: Vec<&String> = target
+                .prerequisites
+                .iter()
+                .filter(|prerequisite| targets.contains_key(*prerequisite))
+                .collect();
+
+            let mut make = format!("make -C {} -f {}", dir, file);
+            for dependency in &dependencies {
+                make.push_str(&format!(" -o {}", shell_quote(dependency)));
+            }
+            make.push_str(&format!(" {}", shell_quote(name)));
+
+            let mut actions: Vec<Action> = dependencies
+                .iter()
+                .map(|dependency| {
+                    Action::Task(TaskCall {
+                        name: String::clone(dependency),
+                    })
+                })
+                .collect();
+            actions.push(command(make));
+
+            Task {
+                name: name.clone(),
+                description: target.description.clone(),
+                private: name.starts_with('_'),
+                actions,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// One task per recipe, run through just so it resolves dependencies and parameters itself.
+/// The comment above a recipe becomes its description and `alias` lines become aliases
+fn justfile_recipes(content: &str, path: &Utf8Path) -> Vec<Task> {
+    let mut tasks: Vec<Task> = vec![];
+    let mut aliases: Vec<(String, String)> = vec![];
+    let mut comment: Option<String> = None;
+    let mut private = false;
+
+    let justfile = shell_quote(path.as_str());
+    let dir = shell_quote(directory(path));
+
+    for line in content.lines() {
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            (comment, private) = (None, false);
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(String::from(text.trim()));
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        let first_word = line.split_whitespace().next().unwrap_or_default();
+        if first_word == "alias" {
+            if let Some((alias, recipe)) = line["alias".len()..].split_once(":=") {
+                aliases.push((String::from(alias.trim()), String::from(recipe.trim())));
+            }
+            continue;
+        }
+        if matches!(first_word, "set" | "export" | "import" | "mod" | "unexport") {
+            continue;
+        }
+
+        let header = line.strip_prefix('@').unwrap_or(line);
+        let Some(colon) = header.find(':') else {
+            continue;
+        };
+        if header[colon + 1..].starts_with('=') {
+            continue;
+        }
+
+        let Some(name) = header[..colon].split_whitespace().next() else {
+            continue;
+        };
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            continue;
+        }
+
+        tasks.push(Task {
+            name: String::from(name),
+            description: comment.take(),
+            private: private || name.starts_with('_'),
+            actions: vec![command(format!(
+                "just --justfile {} --working-directory {} {}",
+                justfile,
+                dir,
+                shell_quote(name)
+            ))],
+            ..Default::default()
+        });
+        private = false;
+    }
+
+    for (alias, recipe) in aliases {
+        if let Some(task) = tasks.iter_mut().find(|task| task.name == recipe) {
+            task.aliases.push(alias);
+        }
+    }
+
+    tasks
+}
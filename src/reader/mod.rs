@@ -1,38 +1,229 @@
-mod node;
+mod foreign;
+pub(crate) mod node;
 mod parser;
+mod structured;
+
+pub use parser::{ParserError, ParserErrorData};
+pub(crate) use parser::Scope;
 
 use camino::Utf8Path;
 use kdl::KdlDocument;
-use std::{fs, io};
+use miette::SourceSpan;
+use std::{error, fmt, fs, io};
 
-use crate::tasks::TaskFile;
+use crate::tasks::{Action, Import, ImportFormat, TaskFile};
+use crate::utils::line_col;
 
+/// Error returned when a task file cannot be read, parsed or imported
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum TaskFileReadError {
     Io(io::Error),
     Syntax(kdl::KdlError),
-    Parser(parser::ParserError),
+    Parser(parser::ParserError, Option<Location>),
+    /// A YAML, TOML or JSON task file that does not deserialize or describe a valid task file
+    Structured(String),
+}
+
+/// Language a task file is written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Kdl,
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// Picks the format by file extension, files without a known extension are read as KDL
+    pub fn from_path(path: &Utf8Path) -> Self {
+        match path.extension() {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => Format::Kdl,
+        }
+    }
 }
 
+/// Position of a node in the task file source
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    fn from_span(content: &str, span: SourceSpan) -> Self {
+        let (line, column) = line_col(content, span.offset());
+
+        Self { line, column }
+    }
+}
+
+/// Reads a task file in the format matching its extension, including everything it imports
 pub fn open_and_read(path: &Utf8Path) -> Result<TaskFile, TaskFileReadError> {
     let content = fs::read_to_string(path)?;
 
-    read(content)
+    read_format(content, Format::from_path(path))
+}
+
+/// Parses task file source in the given format. Imports are resolved relative to the current
+/// directory
+pub fn read_format(content: String, format: Format) -> Result<TaskFile, TaskFileReadError> {
+    read_format_in(content, format, Utf8Path::new(""))
+}
+
+/// Parses task file source in the given format, resolving imports relative to `dir`
+pub fn read_format_in(
+    content: String,
+    format: Format,
+    dir: &Utf8Path,
+) -> Result<TaskFile, TaskFileReadError> {
+    match format {
+        Format::Kdl => read_in(content, dir),
+        format => structured::read(&content, format, dir),
+    }
+}
+
+/// Parses KDL task file source. Imports are resolved relative to the current directory
+pub fn read(content: String) -> Result<TaskFile, TaskFileReadError> {
+    read_in(content, Utf8Path::new(""))
 }
 
-fn read(content: String) -> Result<TaskFile, TaskFileReadError> {
+/// Parses KDL task file source, resolving imports relative to `dir`
+pub fn read_in(content: String, dir: &Utf8Path) -> Result<TaskFile, TaskFileReadError> {
     let doc: KdlDocument = content.parse()?;
     let mut task_file = TaskFile::default();
-    let mut context = parser::Context::default();
+    let mut context = parser::Context::for_source(&content, dir);
 
     for node in doc.nodes() {
-        parser::parse_node(node, &mut task_file, &mut context)?;
+        if let Err(e) = parser::parse_node(node, &mut task_file, &mut context) {
+            let location = context
+                .error_span()
+                .map(|span| Location::from_span(&content, span));
+
+            return Err(TaskFileReadError::Parser(e, location));
+        }
     }
 
     Ok(task_file)
 }
 
+/// Reads an imported task file or build definition and merges its variables and tasks into
+/// `task_file`. `path` is relative to `dir`, as are the imports of an imported task file.
+/// Imported task names, aliases and the `task` actions calling them are prefixed with `prefix:`
+fn import(
+    task_file: &mut TaskFile,
+    dir: &Utf8Path,
+    path: &Utf8Path,
+    prefix: Option<&str>,
+    format: Option<ImportFormat>,
+) -> Result<(), TaskFileReadError> {
+    let written = path;
+    let path = &dir.join(path);
+    let imported_file = match format {
+        None => {
+            let content = fs::read_to_string(path)?;
+            read_format_in(content, Format::from_path(path), dir)?
+        }
+        Some(format) => foreign::read(path, format)?,
+    };
+
+    task_file.imports.push(Import {
+        path: written.to_string(),
+        prefix: prefix.map(String::from),
+        format,
+    });
+
+    for mut variable in imported_file.variables {
+        variable.source.get_or_insert_with(|| path.to_path_buf());
+        task_file.variables.push(variable);
+    }
+
+    let prefixed = |name: &str| format!("{}:{}", prefix.unwrap_or(""), name);
+    let imported_names: Vec<String> = imported_file
+        .tasks
+        .iter()
+        .flat_map(|(name, task)| task.aliases.iter().chain([name]))
+        .cloned()
+        .collect();
+
+    for (name, mut task) in imported_file.tasks {
+        task.aliases = task.aliases.iter().map(|alias| prefixed(alias)).collect();
+        task.source.get_or_insert_with(|| path.to_path_buf());
+
+        for action in task.actions.iter_mut() {
+            if let Action::Task(call) = action {
+                if imported_names.contains(&call.name) {
+                    call.name = prefixed(&call.name);
+                }
+            }
+        }
+
+        let name = prefixed(&name);
+        if let Some(name) = task_file.duplicate_name(&name, &task) {
+            return Err(TaskFileReadError::Parser(
+                ParserError::duplicate_name(name),
+                None,
+            ));
+        }
+
+        task_file.tasks.insert(name, task);
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for TaskFileReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskFileReadError::Io(e) => write!(f, "{}", e),
+            TaskFileReadError::Structured(message) => write!(f, "{}", message),
+            TaskFileReadError::Parser(e, None) => write!(f, "{}", e),
+            TaskFileReadError::Parser(e, Some(location)) => write!(
+                f,
+                "{} (line {}, column {})",
+                e, location.line, location.column
+            ),
+            TaskFileReadError::Syntax(e) => {
+                let Some(diagnostic) = e.diagnostics.first() else {
+                    return write!(f, "{}", e);
+                };
+
+                let (line, column) = line_col(&e.input, diagnostic.span.offset());
+                write!(
+                    f,
+                    "Syntax error at line {}, column {}: {}",
+                    line,
+                    column,
+                    diagnostic
+                        .message
+                        .as_deref()
+                        .or(diagnostic.label.as_deref())
+                        .unwrap_or("invalid KDL")
+                )?;
+
+                if let Some(help) = &diagnostic.help {
+                    write!(f, " ({})", help)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for TaskFileReadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TaskFileReadError::Io(e) => Some(e),
+            TaskFileReadError::Syntax(e) => Some(e),
+            TaskFileReadError::Parser(e, _) => Some(e),
+            TaskFileReadError::Structured(_) => None,
+        }
+    }
+}
+
 impl From<io::Error> for TaskFileReadError {
     fn from(e: io::Error) -> Self {
         TaskFileReadError::Io(e)
@@ -47,6 +238,6 @@ impl From<kdl::KdlError> for TaskFileReadError {
 
 impl From<parser::ParserError> for TaskFileReadError {
     fn from(e: parser::ParserError) -> Self {
-        TaskFileReadError::Parser(e)
+        TaskFileReadError::Parser(e, None)
     }
 }
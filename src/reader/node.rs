@@ -1,10 +1,15 @@
 use super::parser::{self, Scope};
+use crate::tasks::{ImportFormat, MessageLevel, PromptKind, VariableType};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NodeType {
     Import,
     Variables,
     Variable,
     ListItem,
+    MapEntry,
+    Enum,
+    Prompt,
     Tasks,
     Task,
     Actions,
@@ -12,14 +17,625 @@ pub enum NodeType {
     If,
     ActionTask,
     ActionCd,
+    Confirm,
+    Script,
+    FilePath,
+    FileTransfer,
+    Write,
+    Chmod,
+    Echo,
+    Fail,
+    Requires,
+    Requirement,
+    Default,
+    Alias,
+}
+
+/// Type of an argument or property value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    String,
+    Bool,
+    /// Any string, number, boolean or null
+    Scalar,
+    /// One of the given strings
+    OneOf(&'static [&'static str]),
+}
+
+pub struct ArgumentSpec {
+    pub name: &'static str,
+    pub value_type: ValueType,
+    pub required: bool,
+    pub variadic: bool,
+    pub doc: &'static str,
+}
+
+pub struct PropertySpec {
+    pub name: &'static str,
+    pub value_type: ValueType,
+    pub doc: &'static str,
+}
+
+/// Describes what a node may contain and where it may appear
+pub struct NodeSpec {
+    /// Names the node is recognised by. Empty for nodes named by the user, like tasks and variables
+    pub names: &'static [&'static str],
+    pub doc: &'static str,
+    pub arguments: &'static [ArgumentSpec],
+    pub properties: &'static [PropertySpec],
+    /// Scope the children of the node are read in, `None` for nodes without children
+    pub children: Option<Scope>,
+    pub scopes: &'static [Scope],
+}
+
+const fn required(name: &'static str, value_type: ValueType, doc: &'static str) -> ArgumentSpec {
+    ArgumentSpec {
+        name,
+        value_type,
+        required: true,
+        variadic: false,
+        doc,
+    }
+}
+
+const fn optional(name: &'static str, value_type: ValueType, doc: &'static str) -> ArgumentSpec {
+    ArgumentSpec {
+        name,
+        value_type,
+        required: false,
+        variadic: false,
+        doc,
+    }
+}
+
+const fn property(name: &'static str, value_type: ValueType, doc: &'static str) -> PropertySpec {
+    PropertySpec {
+        name,
+        value_type,
+        doc,
+    }
+}
+
+const ACTION_SCOPES: &[Scope] = &[Scope::Task, Scope::Actions];
+
+const SHELL_PROPERTY: PropertySpec = property(
+    "shell",
+    ValueType::String,
+    "Shell the command is run with, `sh` by default",
+);
+
+pub const NODE_TYPES: &[NodeType] = &[
+    NodeType::Import,
+    NodeType::Default,
+    NodeType::Variables,
+    NodeType::Variable,
+    NodeType::ListItem,
+    NodeType::MapEntry,
+    NodeType::Enum,
+    NodeType::Prompt,
+    NodeType::Tasks,
+    NodeType::Task,
+    NodeType::Alias,
+    NodeType::Requires,
+    NodeType::Requirement,
+    NodeType::Actions,
+    NodeType::Cmd,
+    NodeType::If,
+    NodeType::Script,
+    NodeType::ActionTask,
+    NodeType::ActionCd,
+    NodeType::Confirm,
+    NodeType::FilePath,
+    NodeType::FileTransfer,
+    NodeType::Write,
+    NodeType::Chmod,
+    NodeType::Echo,
+    NodeType::Fail,
+];
+
+static IMPORT_SPEC: NodeSpec = NodeSpec {
+    names: &["import"],
+    doc: "Reads the tasks and variables of another task file, or the tasks of a package.json, \
+          Makefile or justfile",
+    arguments: &[required(
+        "file",
+        ValueType::String,
+        "Path of the file to import",
+    )],
+    properties: &[
+        property(
+            "prefix",
+            ValueType::String,
+            "Imported tasks and variables are named `prefix:name`",
+        ),
+        property(
+            "format",
+            ValueType::OneOf(ImportFormat::NAMES),
+            "Reads the file as the build definition of another tool instead of a task file",
+        ),
+    ],
+    children: None,
+    scopes: &[Scope::Global],
+};
+
+static DEFAULT_SPEC: NodeSpec = NodeSpec {
+    names: &["default"],
+    doc: "Task run when jatr is called without a task name",
+    arguments: &[required(
+        "task",
+        ValueType::String,
+        "Name or alias of the task",
+    )],
+    properties: &[],
+    children: None,
+    scopes: &[Scope::Global],
+};
+
+static VARIABLES_SPEC: NodeSpec = NodeSpec {
+    names: &["variables", "vars"],
+    doc: "Variables available to templates, shared by all tasks when declared at the top level",
+    arguments: &[],
+    properties: &[],
+    children: Some(Scope::Variables),
+    scopes: &[Scope::Global, Scope::Task],
+};
+
+static VARIABLE_SPEC: NodeSpec = NodeSpec {
+    names: &[],
+    doc: "Variable named by the node. Its value is the argument, a list of `-` items, a map of \
+          named entries, the output of a `cmd` or the content of a file",
+    arguments: &[optional(
+        "value",
+        ValueType::Scalar,
+        "Value of the variable",
+    )],
+    properties: &[
+        property(
+            "type",
+            ValueType::OneOf(VariableType::NAMES),
+            "Converts the value. Command output is trimmed, split into lines for `list` and \
+             parsed for `json`",
+        ),
+        property(
+            "required",
+            ValueType::Bool,
+            "The variable must not be null or empty. It may then be declared without a value",
+        ),
+        property(
+            "pattern",
+            ValueType::String,
+            "Regular expression the value must match",
+        ),
+        property(
+            "from_file",
+            ValueType::String,
+            "File the value is read from, relative to the working directory. JSON, YAML and TOML \
+             files are parsed, others read as text",
+        ),
+        property(
+            "from_cmd_json",
+            ValueType::String,
+            "Command whose output is parsed as JSON",
+        ),
+        property(
+            "from_env",
+            ValueType::String,
+            "Environment variable the value is read from",
+        ),
+        property(
+            "secret",
+            ValueType::Bool,
+            "Masks the value as `***` in printed commands, logs and errors, unless it is \
+             shorter than 4 characters. Variables asked with `password` are always secret",
+        ),
+        property(
+            "cache",
+            ValueType::String,
+            "Keeps the output of the command on disk for a duration like `90s`, `15m`, `1h` \
+             or `7d`",
+        ),
+    ],
+    children: Some(Scope::Variable),
+    scopes: &[Scope::Variables],
+};
+
+static LIST_ITEM_SPEC: NodeSpec = NodeSpec {
+    names: &["-"],
+    doc: "Item of a list variable. Its value is the argument, a list of `-` items or a map of \
+          named entries",
+    arguments: &[optional("value", ValueType::Scalar, "Value of the item")],
+    properties: &[],
+    children: Some(Scope::Map),
+    scopes: &[Scope::Variable, Scope::Map],
+};
+
+static MAP_ENTRY_SPEC: NodeSpec = NodeSpec {
+    names: &[],
+    doc: "Entry of a map variable named by the node. Its value is the argument, a list of `-` \
+          items or nested entries. Directly in a variable, children named `cmd`, `enum`, a \
+          prompt or an action are not entries unless the variable has another entry, which \
+          makes every child but `-` an entry",
+    arguments: &[optional("value", ValueType::Scalar, "Value of the entry")],
+    properties: &[],
+    children: Some(Scope::Map),
+    scopes: &[Scope::Variable, Scope::Map],
+};
+
+static ENUM_SPEC: NodeSpec = NodeSpec {
+    names: &["enum"],
+    doc: "Values the variable may take. Each item of a list variable must be one of them",
+    arguments: &[ArgumentSpec {
+        name: "choice",
+        value_type: ValueType::Scalar,
+        required: true,
+        variadic: true,
+        doc: "Allowed value",
+    }],
+    properties: &[],
+    children: None,
+    scopes: &[Scope::Variable],
+};
+
+static PROMPT_SPEC: NodeSpec = NodeSpec {
+    names: PromptKind::NAMES,
+    doc: "Asks for the value when the task runs: `prompt` for text, `password` without echo, \
+          `select` and `multiselect` for one or more of the variable's `enum` choices. Skipped \
+          when the variable is overridden",
+    arguments: &[required(
+        "message",
+        ValueType::String,
+        "Question shown to the user",
+    )],
+    properties: &[property(
+        "default",
+        ValueType::String,
+        "Answer of a `prompt` left empty",
+    )],
+    children: None,
+    scopes: &[Scope::Variable],
+};
+
+static TASKS_SPEC: NodeSpec = NodeSpec {
+    names: &["tasks"],
+    doc: "Task definitions, one child per task",
+    arguments: &[],
+    properties: &[],
+    children: Some(Scope::Tasks),
+    scopes: &[Scope::Global],
+};
+
+static TASK_SPEC: NodeSpec = NodeSpec {
+    names: &[],
+    doc: "Task named by the node",
+    arguments: &[optional(
+        "description",
+        ValueType::String,
+        "Description shown in the task list",
+    )],
+    properties: &[property(
+        "private",
+        ValueType::Bool,
+        "Hides the task from the task list. Tasks starting with `_` are private by default",
+    )],
+    children: Some(Scope::Task),
+    scopes: &[Scope::Tasks],
+};
+
+static ALIAS_SPEC: NodeSpec = NodeSpec {
+    names: &["alias", "aliases"],
+    doc: "Other names the task can be run by",
+    arguments: &[ArgumentSpec {
+        name: "alias",
+        value_type: ValueType::String,
+        required: true,
+        variadic: true,
+        doc: "Alias of the task",
+    }],
+    properties: &[],
+    children: None,
+    scopes: &[Scope::Task],
+};
+
+static REQUIRES_SPEC: NodeSpec = NodeSpec {
+    names: &["requires"],
+    doc: "Tools, environment variables and files the task needs. They are checked before the \
+          run starts, together with those of the tasks it calls, and everything missing is \
+          reported at once",
+    arguments: &[],
+    properties: &[],
+    children: Some(Scope::Requires),
+    scopes: &[Scope::Task],
+};
+
+static REQUIREMENT_SPEC: NodeSpec = NodeSpec {
+    names: &["tool", "env", "file"],
+    doc: "`tool` requires a program in PATH, `env` a set environment variable and `file` a file \
+          or directory relative to the working directory",
+    arguments: &[required(
+        "name",
+        ValueType::String,
+        "Program, environment variable or path",
+    )],
+    properties: &[
+        property(
+            "version",
+            ValueType::String,
+            "Semver requirement like `>=24` the output of `<tool> --version` must match. Only \
+             for `tool`",
+        ),
+        property(
+            "hint",
+            ValueType::String,
+            "How to meet the requirement, shown when it is not",
+        ),
+    ],
+    children: None,
+    scopes: &[Scope::Requires],
+};
+
+static ACTIONS_SPEC: NodeSpec = NodeSpec {
+    names: &["actions"],
+    doc: "Actions of the task. They can also be written directly in the task",
+    arguments: &[],
+    properties: &[],
+    children: Some(Scope::Actions),
+    scopes: &[Scope::Task],
+};
+
+static CMD_SPEC: NodeSpec = NodeSpec {
+    names: &["cmd", "-"],
+    doc: "Runs a shell command. In a variable, the output of the command becomes its value",
+    arguments: &[required(
+        "command",
+        ValueType::String,
+        "Command line, rendered as a template",
+    )],
+    properties: &[SHELL_PROPERTY],
+    children: None,
+    scopes: &[Scope::Task, Scope::Actions, Scope::Variable],
+};
+
+static IF_SPEC: NodeSpec = NodeSpec {
+    names: &["if"],
+    doc: "Runs a shell command and skips the rest of the task unless it succeeds",
+    arguments: &[required(
+        "command",
+        ValueType::String,
+        "Condition command line, rendered as a template",
+    )],
+    properties: &[SHELL_PROPERTY],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static ACTION_TASK_SPEC: NodeSpec = NodeSpec {
+    names: &["task"],
+    doc: "Runs another task",
+    arguments: &[required(
+        "task",
+        ValueType::String,
+        "Name or alias of the task to run",
+    )],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static SCRIPT_SPEC: NodeSpec = NodeSpec {
+    names: &["script"],
+    doc: "Runs a multi-line script from a temporary file. Shell scripts stop at the first \
+          failing command, and line numbers in errors are those of the task file",
+    arguments: &[required(
+        "body",
+        ValueType::String,
+        "Script, usually a multi-line string. A `#!` first line picks the interpreter",
+    )],
+    properties: &[property(
+        "interpreter",
+        ValueType::String,
+        "Command the script file is passed to, like `python3` or `node`. The shebang or the \
+         shell by default",
+    )],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+const PATH_ARGUMENT: ArgumentSpec = required(
+    "path",
+    ValueType::String,
+    "Path, relative to the working directory",
+);
+
+static FILE_PATH_SPEC: NodeSpec = NodeSpec {
+    names: &["mkdir", "rm", "touch"],
+    doc: "Creates a directory and its parents (`mkdir`), removes a file or directory tree if it \
+          exists (`rm`) or creates a file and updates its modification time (`touch`), without \
+          a shell. `rm` refuses the working directory and the directories containing it",
+    arguments: &[PATH_ARGUMENT],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static FILE_TRANSFER_SPEC: NodeSpec = NodeSpec {
+    names: &["copy", "move", "symlink"],
+    doc: "Copies or moves a file or directory tree, or creates a symlink, without a shell. \
+          Neither moves nor overwrites the working directory or the directories containing it",
+    arguments: &[
+        required(
+            "from",
+            ValueType::String,
+            "Source path. For `symlink` the target the link points to, kept as written",
+        ),
+        required("to", ValueType::String, "Destination path, or the link"),
+    ],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static WRITE_SPEC: NodeSpec = NodeSpec {
+    names: &["write"],
+    doc: "Writes a file, creating its directory. The content is a template",
+    arguments: &[
+        PATH_ARGUMENT,
+        required("content", ValueType::String, "Content of the file"),
+    ],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static CHMOD_SPEC: NodeSpec = NodeSpec {
+    names: &["chmod"],
+    doc: "Changes the permissions of a file. Only the write bits are used on Windows",
+    arguments: &[
+        PATH_ARGUMENT,
+        required(
+            "mode",
+            ValueType::Scalar,
+            "Octal mode, like `755` or `\"0644\"`",
+        ),
+    ],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static ECHO_SPEC: NodeSpec = NodeSpec {
+    names: &["echo"],
+    doc: "Prints a message without a shell. Warnings and errors go to stderr",
+    arguments: &[required(
+        "message",
+        ValueType::String,
+        "Message, rendered as a template",
+    )],
+    properties: &[property(
+        "level",
+        ValueType::OneOf(MessageLevel::NAMES),
+        "Level of the message, `info` by default",
+    )],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static FAIL_SPEC: NodeSpec = NodeSpec {
+    names: &["fail"],
+    doc: "Stops the run with an error message, usually after an `if` precondition",
+    arguments: &[required(
+        "message",
+        ValueType::String,
+        "Error message, rendered as a template",
+    )],
+    properties: &[property(
+        "code",
+        ValueType::Scalar,
+        "Exit code jatr ends with, from 1 to 255. 1 by default",
+    )],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static CONFIRM_SPEC: NodeSpec = NodeSpec {
+    names: &["confirm"],
+    doc: "Asks a yes/no question and aborts the run unless answered yes",
+    arguments: &[required(
+        "message",
+        ValueType::String,
+        "Question shown to the user",
+    )],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+static ACTION_CD_SPEC: NodeSpec = NodeSpec {
+    names: &["cd"],
+    doc: "Changes the working directory of the actions that follow",
+    arguments: &[required(
+        "path",
+        ValueType::String,
+        "Directory to change to, relative to the current one",
+    )],
+    properties: &[],
+    children: None,
+    scopes: ACTION_SCOPES,
+};
+
+impl NodeSpec {
+    pub fn property(&self, name: &str) -> Option<&'static PropertySpec> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn property_names(&self) -> Vec<&'static str> {
+        self.properties.iter().map(|p| p.name).collect()
+    }
+}
+
+impl NodeType {
+    pub fn spec(&self) -> &'static NodeSpec {
+        match self {
+            NodeType::Import => &IMPORT_SPEC,
+            NodeType::Default => &DEFAULT_SPEC,
+            NodeType::Variables => &VARIABLES_SPEC,
+            NodeType::Variable => &VARIABLE_SPEC,
+            NodeType::ListItem => &LIST_ITEM_SPEC,
+            NodeType::MapEntry => &MAP_ENTRY_SPEC,
+            NodeType::Enum => &ENUM_SPEC,
+            NodeType::Prompt => &PROMPT_SPEC,
+            NodeType::Tasks => &TASKS_SPEC,
+            NodeType::Task => &TASK_SPEC,
+            NodeType::Alias => &ALIAS_SPEC,
+            NodeType::Actions => &ACTIONS_SPEC,
+            NodeType::Cmd => &CMD_SPEC,
+            NodeType::If => &IF_SPEC,
+            NodeType::ActionTask => &ACTION_TASK_SPEC,
+            NodeType::ActionCd => &ACTION_CD_SPEC,
+            NodeType::Confirm => &CONFIRM_SPEC,
+            NodeType::Script => &SCRIPT_SPEC,
+            NodeType::FilePath => &FILE_PATH_SPEC,
+            NodeType::FileTransfer => &FILE_TRANSFER_SPEC,
+            NodeType::Write => &WRITE_SPEC,
+            NodeType::Chmod => &CHMOD_SPEC,
+            NodeType::Echo => &ECHO_SPEC,
+            NodeType::Fail => &FAIL_SPEC,
+            NodeType::Requires => &REQUIRES_SPEC,
+            NodeType::Requirement => &REQUIREMENT_SPEC,
+        }
+    }
+
+    pub fn allowed_in(&self, scope: &Scope) -> bool {
+        self.spec().scopes.contains(scope)
+    }
+}
+
+/// Returns the node names that may be written inside the given scope
+pub fn names_in_scope(scope: &Scope) -> Vec<&'static str> {
+    let mut names = vec![];
+    for node_type in NODE_TYPES.iter().filter(|t| t.allowed_in(scope)) {
+        for name in node_type.spec().names {
+            if !names.contains(name) {
+                names.push(*name);
+            }
+        }
+    }
+
+    names
 }
 
 pub fn get_node_type_by_name(name: &str, context: &parser::Context) -> Option<NodeType> {
+    node_type_in_scope(name, &context.current_scope_type())
+}
+
+/// Resolves a node name the way the parser does when it is written inside `scope`
+pub fn node_type_in_scope(name: &str, scope: &Scope) -> Option<NodeType> {
     match name {
+        "-" if matches!(scope, Scope::Map) => Some(NodeType::ListItem),
+        _ if matches!(scope, Scope::Map) => Some(NodeType::MapEntry),
         "import" => Some(NodeType::Import),
         "variables" | "vars" => Some(NodeType::Variables),
         "cmd" => Some(NodeType::Cmd),
-        "-" => match context.current_scope_type() {
+        "-" => match scope {
             Scope::Actions => Some(NodeType::Cmd),
             Scope::Task => Some(NodeType::Cmd),
             _ => Some(NodeType::ListItem),
@@ -29,9 +645,26 @@ pub fn get_node_type_by_name(name: &str, context: &parser::Context) -> Option<No
         "if" => Some(NodeType::If),
         "task" => Some(NodeType::ActionTask),
         "cd" => Some(NodeType::ActionCd),
-        _ => match context.current_scope_type() {
+        "confirm" => Some(NodeType::Confirm),
+        "script" => Some(NodeType::Script),
+        "mkdir" | "rm" | "touch" => Some(NodeType::FilePath),
+        "copy" | "move" | "symlink" => Some(NodeType::FileTransfer),
+        "write" => Some(NodeType::Write),
+        "chmod" => Some(NodeType::Chmod),
+        "echo" => Some(NodeType::Echo),
+        "fail" => Some(NodeType::Fail),
+        "enum" if matches!(scope, Scope::Variable) => Some(NodeType::Enum),
+        "prompt" | "password" | "select" | "multiselect" if matches!(scope, Scope::Variable) => {
+            Some(NodeType::Prompt)
+        }
+        "default" if matches!(scope, Scope::Global) => Some(NodeType::Default),
+        "alias" | "aliases" if matches!(scope, Scope::Task) => Some(NodeType::Alias),
+        "requires" if matches!(scope, Scope::Task) => Some(NodeType::Requires),
+        "tool" | "env" | "file" if matches!(scope, Scope::Requires) => Some(NodeType::Requirement),
+        _ => match scope {
             Scope::Tasks => Some(NodeType::Task),
             Scope::Variables => Some(NodeType::Variable),
+            Scope::Variable => Some(NodeType::MapEntry),
             _ => None,
         },
     }
@@ -1,36 +1,47 @@
-use super::{node, open_and_read};
-use crate::tasks::{ActionCommand, Task, TaskCall, Value};
+use super::{import, node};
+use crate::tasks::{ActionCommand, Constraints, ImportFormat, Task, TaskCall, Value, VariableType};
 use crate::{
-    tasks::{Action, TaskFile, Variable, VariableValue},
-    utils::kdl_value_to_value,
+    tasks::{
+        Action, ActionScript, Failure, FileAction, Message, MessageLevel, Prompt, PromptKind,
+        Requirement, RequirementKind, TaskFile, Variable, VariableValue,
+    },
+    utils::{did_you_mean, kdl_value_to_value, line_col, parse_duration, parse_mode},
     S,
 };
-use camino::Utf8Path;
-use kdl::KdlNode;
+use camino::{Utf8Path, Utf8PathBuf};
+use indexmap::IndexMap;
+use kdl::{KdlEntry, KdlNode, KdlValue};
+use miette::SourceSpan;
+use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Scope {
     Global,
     Variables,
     Variable,
+    Map,
     Tasks,
     Task,
     Actions,
+    Requires,
 }
 
 #[derive(Debug)]
 pub struct ContextError(String);
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct ParserError(String, ParserErrorData);
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum ParserErrorData {
     UnknownNode { name: String },
+    UnknownProperty { node: String, name: String },
+    MisplacedNode { name: String },
+    UnexpectedArgument { node: String },
+    UnexpectedChildren { node: String },
     MissingArgument { name: String },
     MissingBody,
+    DuplicateName { name: String },
 
     ImportError,
     ContextError(ContextError),
@@ -43,12 +54,21 @@ pub struct ContextScope {
     actions: Vec<Action>,
     variables: Vec<Variable>,
     list_items: Vec<Value>,
+    map_entries: IndexMap<String, Value>,
+    choices: Vec<Value>,
+    prompts: Vec<Prompt>,
+    requirements: Vec<Requirement>,
     task: Task,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Context {
     scopes: Vec<ContextScope>,
+    spans: Vec<SourceSpan>,
+    /// Source of the task file, to find the lines of script bodies
+    source: String,
+    /// Directory imports are resolved against, empty for the working directory
+    dir: Utf8PathBuf,
 }
 
 pub fn parse_node(
@@ -57,20 +77,41 @@ pub fn parse_node(
     context: &mut Context,
 ) -> Result<(), ParserError> {
     let node_name = node.name().value();
+    let scope = context.current_scope_type();
+    context.spans.push(node.span());
+
     let Some(node_type) = node::get_node_type_by_name(node_name, context) else {
         return Err(ParserError(
-            format!("Unknown node '{node_name}'"),
+            format!(
+                "Unknown node '{node_name}' in {}{}",
+                scope,
+                suggestion(node_name, &node::names_in_scope(&scope))
+            ),
             ParserErrorData::UnknownNode {
                 name: node_name.to_string(),
             },
         ));
     };
 
-    match node_type {
+    if !node_type.allowed_in(&scope) {
+        return Err(ParserError(
+            format!("Node '{node_name}' is not allowed in {}", scope),
+            ParserErrorData::MisplacedNode {
+                name: node_name.to_string(),
+            },
+        ));
+    }
+
+    check_node_shape(node, node_type)?;
+
+    let result = match node_type {
         node::NodeType::Import => parse_import_node(node, task_file, context),
         node::NodeType::Variables => parse_variables_node(node, task_file, context),
         node::NodeType::Variable => parse_variable_node(node, task_file, context),
         node::NodeType::ListItem => parse_list_item(node, task_file, context),
+        node::NodeType::MapEntry => parse_map_entry(node, task_file, context),
+        node::NodeType::Enum => parse_enum(node, task_file, context),
+        node::NodeType::Prompt => parse_prompt(node, task_file, context),
         node::NodeType::Cmd => parse_cmd(node, task_file, context),
         node::NodeType::Tasks => parse_tasks(node, task_file, context),
         node::NodeType::Task => parse_task(node, task_file, context),
@@ -78,13 +119,98 @@ pub fn parse_node(
         node::NodeType::If => parse_if(node, task_file, context),
         node::NodeType::ActionTask => parse_action_task(node, task_file, context),
         node::NodeType::ActionCd => parse_action_cd(node, task_file, context),
+        node::NodeType::Confirm => parse_confirm(node, task_file, context),
+        node::NodeType::Script => parse_script(node, task_file, context),
+        node::NodeType::FilePath
+        | node::NodeType::FileTransfer
+        | node::NodeType::Write
+        | node::NodeType::Chmod => parse_file_action(node, task_file, context),
+        node::NodeType::Echo => parse_echo(node, task_file, context),
+        node::NodeType::Fail => parse_fail(node, task_file, context),
+        node::NodeType::Default => parse_default(node, task_file, context),
+        node::NodeType::Alias => parse_alias(node, task_file, context),
+        node::NodeType::Requires => parse_requires(node, task_file, context),
+        node::NodeType::Requirement => parse_requirement(node, task_file, context),
+    };
+
+    if result.is_ok() {
+        context.spans.pop();
+    }
+
+    result
+}
+
+fn check_node_shape(node: &KdlNode, node_type: node::NodeType) -> Result<(), ParserError> {
+    let node_name = node.name().value();
+    let spec = node_type.spec();
+
+    for entry in node.entries() {
+        let Some(property) = entry.name() else {
+            continue;
+        };
+
+        if spec.property(property.value()).is_none() {
+            return Err(ParserError(
+                format!(
+                    "Unknown property '{}' on node '{node_name}'{}",
+                    property.value(),
+                    suggestion(property.value(), &spec.property_names())
+                ),
+                ParserErrorData::UnknownProperty {
+                    node: node_name.to_string(),
+                    name: property.value().to_string(),
+                },
+            ));
+        }
+    }
+
+    let arguments = node.entries().iter().filter(|e| e.name().is_none()).count();
+    let variadic = spec.arguments.last().is_some_and(|a| a.variadic);
+    if arguments > spec.arguments.len() && !variadic {
+        return Err(ParserError(
+            format!(
+                "Node '{node_name}' takes at most {} argument(s), got {}",
+                spec.arguments.len(),
+                arguments
+            ),
+            ParserErrorData::UnexpectedArgument {
+                node: node_name.to_string(),
+            },
+        ));
+    }
+
+    if let Some(missing) = spec.arguments.iter().skip(arguments).find(|a| a.required) {
+        return Err(ParserError(
+            format!("Missing argument '{}' on node '{node_name}'", missing.name),
+            ParserErrorData::MissingArgument {
+                name: missing.name.to_string(),
+            },
+        ));
+    }
+
+    if node.children().is_some() && spec.children.is_none() {
+        return Err(ParserError(
+            format!("Node '{node_name}' does not accept children"),
+            ParserErrorData::UnexpectedChildren {
+                node: node_name.to_string(),
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+fn suggestion(name: &str, candidates: &[&str]) -> String {
+    match did_you_mean(name, candidates) {
+        Some(candidate) => format!(". Did you mean '{}'?", candidate),
+        None => String::new(),
     }
 }
 
 pub fn parse_import_node(
     node: &KdlNode,
     task_file: &mut TaskFile,
-    _context: &mut Context,
+    context: &mut Context,
 ) -> Result<(), ParserError> {
     let Some(file_path) = node.get(0) else {
         return Err(ParserError(
@@ -98,22 +224,29 @@ pub fn parse_import_node(
         None => None,
     };
 
-    let path = Utf8Path::new(file_path.as_string().unwrap());
-    let imported_file = match open_and_read(path) {
-        Ok(file) => file,
-        Err(e) => {
-            return Err(ParserError(
-                format!("Error reading file {:?}", e),
-                ParserErrorData::ImportError,
-            ))
-        }
+    let format = match node.get("format") {
+        None => None,
+        Some(value) => match value.as_string().and_then(ImportFormat::from_name) {
+            Some(format) => Some(format),
+            None => {
+                return Err(ParserError(
+                    format!(
+                        "Unknown import format '{}', expected one of: {}",
+                        value.as_string().unwrap_or_default(),
+                        ImportFormat::NAMES.join(", ")
+                    ),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+        },
     };
 
-    task_file.variables.extend(imported_file.variables);
-    for (name, task) in imported_file.tasks {
-        task_file
-            .tasks
-            .insert(format!("{}:{}", prefix.unwrap_or(""), name), task);
+    let path = Utf8Path::new(file_path.as_string().unwrap());
+    if let Err(e) = import(task_file, &context.dir, path, prefix, format) {
+        return Err(ParserError(
+            format!("Error importing '{}': {}", path, e),
+            ParserErrorData::ImportError,
+        ));
     }
 
     Ok(())
@@ -161,77 +294,353 @@ pub fn parse_variable_node(
     context: &mut Context,
 ) -> Result<(), ParserError> {
     let name = node.name().value();
-    let Some(value) = node.get(0) else {
-        return parse_variable_node_body(node, task_file, context);
+    let mut constraints = parse_constraints(node)?;
+
+    let cache = match node.get("cache") {
+        None => None,
+        Some(prop) => match prop.as_string().and_then(parse_duration) {
+            Some(cache) => Some(cache),
+            None => {
+                return Err(ParserError(
+                    format!("Cache should be a duration like \"1h\", got {}", prop),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+        },
+    };
+
+    // Once a variable has a map entry its body is a map, so entries named like `cmd`, `enum`
+    // or a prompt keep being entries instead of changing the variable's value
+    let is_map = node.children().is_some_and(|children| {
+        children.nodes().iter().any(|child| {
+            node::node_type_in_scope(child.name().value(), &Scope::Variable)
+                == Some(node::NodeType::MapEntry)
+        })
+    });
+
+    context.scope(match is_map {
+        true => Scope::Map,
+        false => Scope::Variable,
+    });
+    if let Some(children) = node.children() {
+        for child in children.nodes() {
+            parse_node(child, task_file, context)?;
+        }
+    }
+
+    let mut scope_context = context.pop_scope();
+
+    if scope_context.actions.len() > 1 {
+        return Err(ParserError(
+            S!("Cannot have more than one action"),
+            ParserErrorData::ContextError(ContextError(S!("Cannot have more than one action"))),
+        ));
+    }
+
+    let mut sources = vec![];
+    if let Some(value) = node.get(0) {
+        sources.push(VariableValue::Static(kdl_value_to_value(value)));
+    }
+    if !scope_context.list_items.is_empty() {
+        sources.push(VariableValue::Static(Value::List(scope_context.list_items)));
+    }
+    if !scope_context.map_entries.is_empty() {
+        sources.push(VariableValue::Static(Value::Map(scope_context.map_entries)));
+    }
+    if let Some(action) = scope_context.actions.pop() {
+        sources.push(VariableValue::Action(action));
+    }
+    sources.extend(scope_context.prompts.drain(..).map(VariableValue::Prompt));
+    if let Some(path) = string_property(node, "from_file")? {
+        sources.push(VariableValue::File(path));
+    }
+    if let Some(env) = string_property(node, "from_env")? {
+        sources.push(VariableValue::Env(env));
+    }
+    if let Some(command) = string_property(node, "from_cmd_json")? {
+        if constraints
+            .value_type
+            .is_some_and(|t| t != VariableType::Json)
+        {
+            return Err(ParserError(
+                format!("Variable '{name}' reads JSON and cannot have another type"),
+                ParserErrorData::InvalidType,
+            ));
+        }
+
+        constraints.value_type = Some(VariableType::Json);
+        sources.push(VariableValue::Action(Action::Command(ActionCommand {
+            command,
+            shell: None,
+            tty: false,
+        })));
+    }
+
+    let value = match sources.len() {
+        0 if constraints.required => VariableValue::Static(Value::Null),
+        0 => {
+            return Err(ParserError(
+                S!("Variable should not be empty"),
+                ParserErrorData::MissingBody,
+            ))
+        }
+        1 => sources.remove(0),
+        _ => {
+            return Err(ParserError(
+                format!(
+                    "Variable '{name}' has more than one value. Use only one of an argument, \
+                     list items, map entries, 'cmd', a prompt, 'from_file', 'from_env' or \
+                     'from_cmd_json'"
+                ),
+                ParserErrorData::UnexpectedArgument {
+                    node: name.to_string(),
+                },
+            ))
+        }
     };
 
+    if cache.is_some() && !matches!(value, VariableValue::Action(_)) {
+        return Err(ParserError(
+            format!("Only variables set by a command can be cached, '{name}' is not"),
+            ParserErrorData::InvalidType,
+        ));
+    }
+
+    // Passwords are always secret
+    let secret = bool_property(node, "secret")?
+        || matches!(&value, VariableValue::Prompt(prompt) if prompt.kind == PromptKind::Password);
+    if secret && cache.is_some() {
+        return Err(ParserError(
+            format!("Secret variable '{name}' cannot be cached on disk"),
+            ParserErrorData::InvalidType,
+        ));
+    }
+
+    if let VariableValue::Prompt(prompt) = &value {
+        let selects = matches!(prompt.kind, PromptKind::Select | PromptKind::MultiSelect);
+        if selects && scope_context.choices.is_empty() {
+            return Err(ParserError(
+                format!(
+                    "Variable '{name}' uses '{}' and needs an 'enum' to choose from",
+                    prompt.kind.name()
+                ),
+                ParserErrorData::MissingBody,
+            ));
+        }
+    }
+
     context.add_variable(Variable {
         name: name.to_string(),
-        value: VariableValue::Static(kdl_value_to_value(value)),
+        value,
+        constraints: Constraints {
+            choices: std::mem::take(&mut scope_context.choices),
+            ..constraints
+        },
+        cache,
+        secret,
+        source: None,
     });
 
     Ok(())
 }
 
-pub fn parse_variable_node_body(
+fn string_property(node: &KdlNode, name: &str) -> Result<Option<String>, ParserError> {
+    match node.get(name) {
+        None => Ok(None),
+        Some(prop) => match prop.as_string() {
+            Some(value) => Ok(Some(String::from(value))),
+            None => Err(ParserError(
+                format!("Property '{name}' should be a string, got {:?}", prop),
+                ParserErrorData::InvalidType,
+            )),
+        },
+    }
+}
+
+fn bool_property(node: &KdlNode, name: &str) -> Result<bool, ParserError> {
+    match node.get(name) {
+        None => Ok(false),
+        Some(prop) => match prop.as_bool() {
+            Some(value) => Ok(value),
+            None => Err(ParserError(
+                format!("Property '{name}' should be a boolean, got {:?}", prop),
+                ParserErrorData::InvalidType,
+            )),
+        },
+    }
+}
+
+/// Reads the `type`, `required` and `pattern` properties of a variable node
+fn parse_constraints(node: &KdlNode) -> Result<Constraints, ParserError> {
+    let value_type = match node.get("type") {
+        None => None,
+        Some(value) => match value.as_string().and_then(VariableType::from_name) {
+            Some(value_type) => Some(value_type),
+            None => {
+                return Err(ParserError(
+                    format!(
+                        "Unknown variable type {}, expected one of: {}",
+                        value,
+                        VariableType::NAMES.join(", ")
+                    ),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+        },
+    };
+
+    let required = match node.get("required") {
+        None => false,
+        Some(prop) => match prop.as_bool() {
+            None => {
+                return Err(ParserError(
+                    format!("Required should be a boolean, got {:?}", prop),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+            Some(required) => required,
+        },
+    };
+
+    let pattern = match node.get("pattern") {
+        None => None,
+        Some(prop) => match prop.as_string() {
+            None => {
+                return Err(ParserError(
+                    format!("Pattern should be a string, got {:?}", prop),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+            Some(pattern) => Some(String::from(pattern)),
+        },
+    };
+
+    Ok(Constraints {
+        value_type,
+        required,
+        choices: vec![],
+        pattern,
+    })
+}
+
+/// Entry of a map variable
+pub fn parse_map_entry(
     node: &KdlNode,
     task_file: &mut TaskFile,
     context: &mut Context,
 ) -> Result<(), ParserError> {
-    let Some(children) = node.children() else {
+    let key = node.name().value();
+    let Some(value) = parse_nested_value(node, task_file, context)? else {
         return Err(ParserError(
-            S!("Vairbale should not be empty"),
+            format!("Map entry '{key}' should not be empty"),
             ParserErrorData::MissingBody,
         ));
     };
 
-    context.scope(Scope::Variable);
-    for child in children.nodes() {
-        parse_node(child, task_file, context)?;
-    }
+    context
+        .current_scope()
+        .map_entries
+        .insert(key.to_string(), value);
 
+    Ok(())
+}
+
+/// Value of a list item or map entry: its argument, a list of `-` children or a map of
+/// named children. `None` when the node has neither
+fn parse_nested_value(
+    node: &KdlNode,
+    task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<Option<Value>, ParserError> {
+    context.scope(Scope::Map);
+    if let Some(children) = node.children() {
+        for child in children.nodes() {
+            parse_node(child, task_file, context)?;
+        }
+    }
     let scope_context = context.pop_scope();
-    let mut actions = scope_context.actions;
-    let list_items = scope_context.list_items;
 
-    if !actions.is_empty() && !list_items.is_empty() {
+    let value = match (
+        node.get(0),
+        scope_context.list_items.is_empty(),
+        scope_context.map_entries.is_empty(),
+    ) {
+        (value, true, true) => value.map(kdl_value_to_value),
+        (None, false, true) => Some(Value::List(scope_context.list_items)),
+        (None, true, false) => Some(Value::Map(scope_context.map_entries)),
+        _ => {
+            let name = node.name().value();
+            return Err(ParserError(
+                format!("'{name}' can only have one of an argument, list items or map entries"),
+                ParserErrorData::UnexpectedArgument {
+                    node: name.to_string(),
+                },
+            ));
+        }
+    };
+
+    Ok(value)
+}
+
+/// Asks the user for the variable value, the kind of question given by the node name
+pub fn parse_prompt(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let name = node.name().value();
+    let kind = PromptKind::from_name(name).unwrap_or(PromptKind::Text);
+
+    let Some(message) = node.get(0).and_then(|message| message.as_string()) else {
         return Err(ParserError(
-            S!("Cannot mix actions and list items inside variable"),
-            ParserErrorData::ContextError(ContextError(S!("Cannot mix actions and list items"))),
+            format!("'{name}' needs a message to show"),
+            ParserErrorData::MissingArgument {
+                name: S!("message"),
+            },
         ));
-    }
-
-    if !list_items.is_empty() {
-        context.add_variable(Variable {
-            name: node.name().value().to_string(),
-            value: VariableValue::Static(Value::List(list_items)),
-        });
-    }
+    };
 
-    if !actions.is_empty() && actions.len() > 1 {
+    let default = string_property(node, "default")?;
+    if default.is_some() && kind != PromptKind::Text {
         return Err(ParserError(
-            S!("Cannot have more than one action"),
-            ParserErrorData::ContextError(ContextError(S!("Cannot have more than one action"))),
+            format!("Only 'prompt' can have a default, not '{name}'"),
+            ParserErrorData::UnknownProperty {
+                node: name.to_string(),
+                name: S!("default"),
+            },
         ));
-    } else if !actions.is_empty() {
-        context.add_variable(Variable {
-            name: node.name().value().to_string(),
-            value: VariableValue::Action(actions.remove(0)),
-        });
     }
 
+    context.current_scope().prompts.push(Prompt {
+        kind,
+        message: String::from(message),
+        default,
+    });
+
     Ok(())
 }
 
-pub fn parse_list_item(
+pub fn parse_enum(
     node: &KdlNode,
     _task_file: &mut TaskFile,
     context: &mut Context,
 ) -> Result<(), ParserError> {
-    match node.get(0) {
-        None => context.add_list_item(Value::Null),
-        Some(value) => context.add_list_item(kdl_value_to_value(value)),
-    }
+    let choices = node
+        .iter()
+        .filter(|e| e.name().is_none())
+        .map(|e| kdl_value_to_value(e.value()));
+    context.current_scope().choices.extend(choices);
+
+    Ok(())
+}
+
+pub fn parse_list_item(
+    node: &KdlNode,
+    task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let value = parse_nested_value(node, task_file, context)?;
+    context.add_list_item(value.unwrap_or(Value::Null));
 
     Ok(())
 }
@@ -290,9 +699,23 @@ pub fn parse_task(
         None => None,
     };
 
+    let private = match node.get("private") {
+        None => task_name.starts_with('_'),
+        Some(prop) => match prop.as_bool() {
+            None => {
+                return Err(ParserError(
+                    format!("Private should be a boolean, got {:?}", prop),
+                    ParserErrorData::InvalidType,
+                ))
+            }
+            Some(private) => private,
+        },
+    };
+
     let task = Task {
         name: String::from(task_name),
         description: description_value.map(|x| x.to_string()),
+        private,
         ..Default::default()
     };
 
@@ -307,8 +730,13 @@ pub fn parse_task(
     let mut scoped_context = context.pop_scope();
     scoped_context.task.actions.extend(scoped_context.actions);
 
+    let task = &scoped_context.task;
+    if let Some(name) = task_file.duplicate_name(&task.name, task) {
+        return Err(ParserError::duplicate_name(name));
+    }
+
     task_file.tasks.insert(
-        String::from(scoped_context.task.name.clone()),
+        scoped_context.task.name.clone(),
         scoped_context.task,
     );
 
@@ -349,10 +777,7 @@ pub fn parse_cmd(
         },
     };
 
-    let tty = match context.current_scope().scope {
-        Scope::Variable => false,
-        _ => true,
-    };
+    let tty = !matches!(context.current_scope().scope, Scope::Variable);
 
     let cmd = ActionCommand {
         command: command.to_string(),
@@ -453,12 +878,292 @@ pub fn parse_action_cd(
     Ok(())
 }
 
-impl Default for Context {
-    fn default() -> Self {
-        Context {
-            scopes: vec![],
+pub fn parse_confirm(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let Some(message) = node.get(0).and_then(|message| message.as_string()) else {
+        return Err(ParserError(
+            S!("Missing argument 'message'"),
+            ParserErrorData::MissingArgument {
+                name: S!("message"),
+            },
+        ));
+    };
+
+    context.add_action(Action::Confirm(String::from(message)));
+
+    Ok(())
+}
+
+pub fn parse_script(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let Some(body) = node.entries().iter().find(|entry| entry.name().is_none()) else {
+        return Err(ParserError(
+            S!("Missing argument 'body'"),
+            ParserErrorData::MissingArgument { name: S!("body") },
+        ));
+    };
+
+    let Some(text) = body.value().as_string() else {
+        return Err(ParserError(
+            format!("Script should be a string, got {:?}", body.value()),
+            ParserErrorData::InvalidType,
+        ));
+    };
+
+    let script = ActionScript {
+        body: String::from(text),
+        interpreter: string_property(node, "interpreter")?,
+        line: context.body_line(body.span()),
+    };
+    context.add_action(Action::Script(script));
+
+    Ok(())
+}
+
+pub fn parse_file_action(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let name = node.name().value();
+    let argument = |index: usize| match node.get(index).and_then(|value| value.as_string()) {
+        Some(value) => Ok(String::from(value)),
+        None => Err(ParserError(
+            format!("Arguments of '{name}' should be strings"),
+            ParserErrorData::InvalidType,
+        )),
+    };
+
+    let action = match name {
+        "mkdir" => FileAction::Mkdir(argument(0)?),
+        "rm" => FileAction::Remove(argument(0)?),
+        "touch" => FileAction::Touch(argument(0)?),
+        "copy" => FileAction::Copy {
+            from: argument(0)?,
+            to: argument(1)?,
+        },
+        "move" => FileAction::Move {
+            from: argument(0)?,
+            to: argument(1)?,
+        },
+        "symlink" => FileAction::Symlink {
+            target: argument(0)?,
+            link: argument(1)?,
+        },
+        "write" => FileAction::Write {
+            path: argument(0)?,
+            content: argument(1)?,
+        },
+        _ => {
+            let mode = match node.get(1) {
+                Some(KdlValue::Integer(mode)) => mode.to_string(),
+                Some(KdlValue::String(mode)) => mode.clone(),
+                _ => String::new(),
+            };
+            let Some(mode) = parse_mode(&mode) else {
+                return Err(ParserError(
+                    format!("Invalid mode '{mode}', expected an octal mode like 755"),
+                    ParserErrorData::InvalidType,
+                ));
+            };
+
+            FileAction::Chmod {
+                path: argument(0)?,
+                mode,
+            }
+        }
+    };
+    context.add_action(Action::File(action));
+
+    Ok(())
+}
+
+pub fn parse_echo(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let Some(text) = node.get(0).and_then(|text| text.as_string()) else {
+        return Err(ParserError(
+            S!("Message of 'echo' should be a string"),
+            ParserErrorData::InvalidType,
+        ));
+    };
+
+    let level = match string_property(node, "level")? {
+        None => MessageLevel::default(),
+        Some(level) => MessageLevel::from_name(&level).ok_or_else(|| {
+            ParserError(
+                format!(
+                    "Unknown level '{level}', expected one of {}",
+                    MessageLevel::NAMES.join(", ")
+                ),
+                ParserErrorData::InvalidType,
+            )
+        })?,
+    };
+
+    context.add_action(Action::Echo(Message {
+        text: String::from(text),
+        level,
+    }));
+
+    Ok(())
+}
+
+pub fn parse_fail(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let Some(message) = node.get(0).and_then(|message| message.as_string()) else {
+        return Err(ParserError(
+            S!("Message of 'fail' should be a string"),
+            ParserErrorData::InvalidType,
+        ));
+    };
+
+    let code = match node.get("code") {
+        None => Some(1),
+        Some(KdlValue::Integer(code)) => i32::try_from(*code).ok(),
+        Some(_) => None,
+    };
+    let Some(code) = code.filter(|code| (1..=255).contains(code)) else {
+        return Err(ParserError(
+            S!("Property 'code' should be an exit code from 1 to 255"),
+            ParserErrorData::InvalidType,
+        ));
+    };
+
+    context.add_action(Action::Fail(Failure {
+        message: String::from(message),
+        code,
+    }));
+
+    Ok(())
+}
+
+pub fn parse_default(
+    node: &KdlNode,
+    task_file: &mut TaskFile,
+    _context: &mut Context,
+) -> Result<(), ParserError> {
+    let Some(task) = node.get(0) else {
+        return Err(ParserError(
+            S!("Missing argument 'task'"),
+            ParserErrorData::MissingArgument { name: S!("task") },
+        ));
+    };
+
+    let Some(task) = task.as_string() else {
+        return Err(ParserError(
+            format!("Default task should be a string, got {:?}", task),
+            ParserErrorData::InvalidType,
+        ));
+    };
+
+    task_file.default = Some(String::from(task));
+
+    Ok(())
+}
+
+pub fn parse_alias(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let aliases: Vec<&KdlEntry> = node.iter().filter(|e| e.name().is_none()).collect();
+    if aliases.is_empty() {
+        return Err(ParserError(
+            S!("Missing argument 'alias'"),
+            ParserErrorData::MissingArgument { name: S!("alias") },
+        ));
+    }
+
+    for alias in aliases {
+        let Some(alias) = alias.value().as_string() else {
+            return Err(ParserError(
+                format!("Alias should be a string, got {:?}", alias.value()),
+                ParserErrorData::InvalidType,
+            ));
+        };
+
+        context.current_scope().task.aliases.push(String::from(alias));
+    }
+
+    Ok(())
+}
+
+pub fn parse_requires(
+    node: &KdlNode,
+    task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    context.scope(Scope::Requires);
+
+    if let Some(children) = node.children() {
+        for child in children.nodes() {
+            parse_node(child, task_file, context)?;
+        }
+    }
+
+    let scoped_context = context.pop_scope();
+    context
+        .current_scope()
+        .task
+        .requirements
+        .extend(scoped_context.requirements);
+
+    Ok(())
+}
+
+pub fn parse_requirement(
+    node: &KdlNode,
+    _task_file: &mut TaskFile,
+    context: &mut Context,
+) -> Result<(), ParserError> {
+    let node_name = node.name().value();
+    let Some(name) = node.get(0).and_then(|name| name.as_string()) else {
+        return Err(ParserError(
+            format!("Argument of '{node_name}' should be a string"),
+            ParserErrorData::InvalidType,
+        ));
+    };
+    let name = String::from(name);
+
+    let version = string_property(node, "version")?;
+    if version.is_some() && node_name != "tool" {
+        return Err(ParserError(
+            S!("Property 'version' can only be set on 'tool'"),
+            ParserErrorData::InvalidType,
+        ));
+    }
+    if let Some(version) = &version {
+        if let Err(e) = semver::VersionReq::parse(version) {
+            return Err(ParserError(
+                format!("Invalid version requirement '{version}': {e}"),
+                ParserErrorData::InvalidType,
+            ));
         }
     }
+
+    let kind = match node_name {
+        "tool" => RequirementKind::Tool { name, version },
+        "env" => RequirementKind::Env(name),
+        _ => RequirementKind::File(name),
+    };
+    context.current_scope().requirements.push(Requirement {
+        kind,
+        hint: string_property(node, "hint")?,
+    });
+
+    Ok(())
 }
 
 impl Default for ContextScope {
@@ -467,6 +1172,10 @@ impl Default for ContextScope {
             scope: Scope::Global,
             actions: vec![],
             list_items: vec![],
+            map_entries: IndexMap::new(),
+            choices: vec![],
+            prompts: vec![],
+            requirements: vec![],
             variables: vec![],
             task: Task::default(),
         }
@@ -492,7 +1201,7 @@ impl Context {
 
     pub fn pop_scope(&mut self) -> ContextScope {
         let scope = self.scopes.pop();
-        scope.unwrap_or_else(|| ContextScope::default())
+        scope.unwrap_or_default()
     }
 
     pub fn current_scope_type(&self) -> Scope {
@@ -511,6 +1220,32 @@ impl Context {
         self.scopes.last_mut().unwrap()
     }
 
+    /// Context for parsing `source`, with imports relative to `dir`
+    pub fn for_source(source: &str, dir: &Utf8Path) -> Self {
+        Self {
+            source: String::from(source),
+            dir: dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// Line of the source the content of a string value starts at. Multi-line strings start
+    /// on the line after their opening quotes
+    pub fn body_line(&self, span: SourceSpan) -> Option<usize> {
+        let text = self.source.get(span.offset()..)?;
+        let (line, _) = line_col(&self.source, span.offset());
+
+        match text.trim_start_matches('#').starts_with("\"\"\"") {
+            true => Some(line + 1),
+            false => Some(line),
+        }
+    }
+
+    /// Span of the innermost node that failed to parse
+    pub fn error_span(&self) -> Option<SourceSpan> {
+        self.spans.last().copied()
+    }
+
     pub fn add_variable(&mut self, variable: Variable) {
         self.current_scope().variables.push(variable);
     }
@@ -524,6 +1259,49 @@ impl Context {
     }
 }
 
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Global => write!(f, "the top level"),
+            Scope::Variables => write!(f, "'variables'"),
+            Scope::Variable => write!(f, "a variable body"),
+            Scope::Map => write!(f, "a map"),
+            Scope::Tasks => write!(f, "'tasks'"),
+            Scope::Task => write!(f, "a task"),
+            Scope::Actions => write!(f, "'actions'"),
+            Scope::Requires => write!(f, "'requires'"),
+        }
+    }
+}
+
+impl ParserError {
+    /// Task name or alias used by more than one task, which the command line cannot tell apart
+    pub(crate) fn duplicate_name(name: &str) -> Self {
+        ParserError(
+            format!("Task name or alias '{}' is used more than once", name),
+            ParserErrorData::DuplicateName {
+                name: String::from(name),
+            },
+        )
+    }
+
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+
+    pub fn data(&self) -> &ParserErrorData {
+        &self.1
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl From<ContextError> for ParserError {
     fn from(e: ContextError) -> Self {
         ParserError(
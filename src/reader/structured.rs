@@ -0,0 +1,677 @@
+//! Reads task files written in YAML, TOML or JSON. All three share one layout:
+//!
+//! ```yaml
+//! default: build
+//! imports:
+//!   - shared.kdl
+//!   - { path: deploy.yaml, prefix: deploy }
+//!   - { path: package.json, prefix: npm, format: npm }
+//! variables:
+//!   profile: debug
+//!   commit: { cmd: git rev-parse HEAD }
+//!   jobs: { cmd: nproc, type: int }
+//!   region: { cmd: "curl -s http://metadata/region", cache: 1h }
+//!   env: { value: dev, enum: [dev, prod] }
+//!   config: { value: { host: localhost, port: 8080 } }
+//!   manifest: { from_file: package.json }
+//!   target: { select: Deploy to?, enum: [staging, prod] }
+//!   token: { from_env: DEPLOY_TOKEN, secret: true }
+//! tasks:
+//!   build:
+//!     description: Builds the project
+//!     aliases: [b]
+//!     requires:
+//!       - { tool: cargo, version: ">=1.70", hint: "Install it with rustup" }
+//!       - { env: CARGO_HOME }
+//!     actions:
+//!       - cargo build --profile {{ profile }}
+//!       - { if: test -f Cargo.lock }
+//!       - { echo: "Building {{ profile }}", level: warn }
+//!       - { confirm: "Deploy {{ profile }}?" }
+//!       - { task: "deploy:upload" }
+//!       - { script: "print('done')", interpreter: python3 }
+//!       - { write: "dist/version.txt", content: "{{ profile }}" }
+//!       - { chmod: bin/run.sh, mode: "755" }
+//! ```
+
+use super::{import, Format, TaskFileReadError};
+use crate::tasks::{
+    Action, ActionCommand, ActionScript, Constraints, Failure, FileAction, ImportFormat, Message,
+    MessageLevel, Prompt, PromptKind, Requirement, RequirementKind, Task, TaskCall, TaskFile,
+    Value, Variable, VariableType, VariableValue,
+};
+use crate::utils::{parse_duration, parse_mode};
+use camino::Utf8Path;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTaskFile {
+    default: Option<String>,
+    #[serde(default)]
+    imports: Vec<RawImport>,
+    #[serde(default)]
+    variables: IndexMap<String, RawVariable>,
+    #[serde(default)]
+    tasks: IndexMap<String, RawTask>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawImport {
+    Path(String),
+    Prefixed(RawPrefixedImport),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPrefixedImport {
+    path: String,
+    prefix: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, default)]
+struct RawTask {
+    description: Option<String>,
+    private: Option<bool>,
+    aliases: Vec<String>,
+    variables: IndexMap<String, RawVariable>,
+    requires: Vec<RawRequirement>,
+    actions: Vec<RawAction>,
+}
+
+/// A task requirement. Exactly one of `tool`, `env` and `file` must be set
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRequirement {
+    tool: Option<String>,
+    env: Option<String>,
+    file: Option<String>,
+    version: Option<String>,
+    hint: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAction {
+    Command(String),
+    Action(Box<RawActionFields>),
+}
+
+/// An action written as a map. Exactly one of `cmd`, `if`, `task`, `cd`, `confirm`, `script`,
+/// `echo`, `fail` and the file actions must be set. `to`, `content` and `mode` complete file
+/// actions
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawActionFields {
+    cmd: Option<String>,
+    #[serde(rename = "if")]
+    if_cmd: Option<String>,
+    task: Option<String>,
+    cd: Option<String>,
+    confirm: Option<String>,
+    script: Option<String>,
+    echo: Option<String>,
+    fail: Option<String>,
+    mkdir: Option<String>,
+    rm: Option<String>,
+    touch: Option<String>,
+    copy: Option<String>,
+    #[serde(rename = "move")]
+    move_from: Option<String>,
+    symlink: Option<String>,
+    write: Option<String>,
+    chmod: Option<String>,
+    shell: Option<String>,
+    interpreter: Option<String>,
+    to: Option<String>,
+    content: Option<String>,
+    mode: Option<RawValue>,
+    level: Option<String>,
+    code: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawVariable {
+    Declared(Box<RawVariableFields>),
+    Value(RawValue),
+}
+
+/// A variable written as a map. Its value is `value`, the output of `cmd` or `from_cmd_json`,
+/// the content of `from_file` or `from_env` or the answer to a prompt, and may be left out when
+/// the variable is `required`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawVariableFields {
+    value: Option<RawValue>,
+    cmd: Option<String>,
+    shell: Option<String>,
+    from_file: Option<String>,
+    from_cmd_json: Option<String>,
+    from_env: Option<String>,
+    prompt: Option<String>,
+    password: Option<String>,
+    select: Option<String>,
+    multiselect: Option<String>,
+    default: Option<String>,
+    #[serde(rename = "type")]
+    value_type: Option<String>,
+    #[serde(default)]
+    required: bool,
+    #[serde(rename = "enum", default)]
+    choices: Vec<RawValue>,
+    pattern: Option<String>,
+    cache: Option<String>,
+    #[serde(default)]
+    secret: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<RawValue>),
+    Map(IndexMap<String, RawValue>),
+    Null(()),
+}
+
+pub fn read(content: &str, format: Format, dir: &Utf8Path) -> Result<TaskFile, TaskFileReadError> {
+    let raw: RawTaskFile = match format {
+        Format::Yaml => serde_yaml::from_str(content).map_err(|e| invalid("YAML", e))?,
+        Format::Toml => toml::from_str(content).map_err(|e| invalid("TOML", e))?,
+        Format::Json => serde_json::from_str(content).map_err(|e| invalid("JSON", e))?,
+        Format::Kdl => unreachable!("KDL task files are read by the parser"),
+    };
+
+    let mut task_file = TaskFile {
+        default: raw.default,
+        ..Default::default()
+    };
+
+    for raw_import in raw.imports {
+        let (path, prefix, format) = match raw_import {
+            RawImport::Path(path) => (path, None, None),
+            RawImport::Prefixed(raw) => (raw.path, raw.prefix, raw.format),
+        };
+
+        let format = match format.as_deref() {
+            None => None,
+            Some(name) => match ImportFormat::from_name(name) {
+                Some(format) => Some(format),
+                None => {
+                    return Err(TaskFileReadError::Structured(format!(
+                        "Unknown import format '{}', expected one of: {}",
+                        name,
+                        ImportFormat::NAMES.join(", ")
+                    )))
+                }
+            },
+        };
+
+        let path = Utf8Path::new(&path);
+        if let Err(e) = import(&mut task_file, dir, path, prefix.as_deref(), format) {
+            return Err(TaskFileReadError::Structured(format!(
+                "Error importing '{}': {}",
+                path, e
+            )));
+        }
+    }
+
+    task_file.variables.extend(variables(raw.variables)?);
+
+    for (name, raw_task) in raw.tasks {
+        let task = task(name, raw_task)?;
+        if let Some(name) = task_file.duplicate_name(&task.name, &task) {
+            return Err(TaskFileReadError::Structured(format!(
+                "Task name or alias '{}' is used more than once",
+                name
+            )));
+        }
+
+        task_file.tasks.insert(task.name.clone(), task);
+    }
+
+    Ok(task_file)
+}
+
+fn invalid(format: &str, e: impl std::fmt::Display) -> TaskFileReadError {
+    TaskFileReadError::Structured(format!("Invalid {} task file: {}", format, e))
+}
+
+fn task(name: String, raw: RawTask) -> Result<Task, TaskFileReadError> {
+    let actions = raw
+        .actions
+        .into_iter()
+        .map(|raw_action| match raw_action {
+            RawAction::Command(command) => Ok(Action::Command(ActionCommand {
+                command,
+                shell: None,
+                tty: true,
+            })),
+            RawAction::Action(fields) => action(*fields).map_err(|message| {
+                TaskFileReadError::Structured(format!("Task '{}': {}", name, message))
+            }),
+        })
+        .collect::<Result<Vec<Action>, TaskFileReadError>>()?;
+
+    let requirements = raw
+        .requires
+        .into_iter()
+        .map(requirement)
+        .collect::<Result<Vec<Requirement>, String>>()
+        .map_err(|message| {
+            TaskFileReadError::Structured(format!("Task '{}': {}", name, message))
+        })?;
+
+    Ok(Task {
+        private: raw.private.unwrap_or(name.starts_with('_')),
+        name,
+        description: raw.description,
+        aliases: raw.aliases,
+        actions,
+        variables: variables(raw.variables)?,
+        requirements,
+        source: None,
+    })
+}
+
+fn action(fields: RawActionFields) -> Result<Action, String> {
+    // `if` output is captured to check for `false`, like in KDL task files
+    let command = |command, tty| ActionCommand {
+        command,
+        shell: fields.shell.clone(),
+        tty,
+    };
+
+    let mut actions: Vec<Action> = file_actions(&fields)?
+        .into_iter()
+        .map(Action::File)
+        .collect();
+    actions.extend(
+        fields
+            .cmd
+            .clone()
+            .map(|cmd| Action::Command(command(cmd, true))),
+    );
+    actions.extend(
+        fields
+            .if_cmd
+            .clone()
+            .map(|cmd| Action::If(command(cmd, false))),
+    );
+    actions.extend(fields.task.map(|name| Action::Task(TaskCall { name })));
+    actions.extend(fields.cd.map(Action::Cd));
+    actions.extend(fields.confirm.map(Action::Confirm));
+    actions.extend(fields.script.map(|body| {
+        Action::Script(ActionScript {
+            body,
+            interpreter: fields.interpreter.clone(),
+            line: None,
+        })
+    }));
+    if let Some(text) = fields.echo {
+        let level = match &fields.level {
+            None => MessageLevel::default(),
+            Some(level) => MessageLevel::from_name(level).ok_or_else(|| {
+                format!(
+                    "unknown level '{}', expected one of {}",
+                    level,
+                    MessageLevel::NAMES.join(", ")
+                )
+            })?,
+        };
+        actions.push(Action::Echo(Message { text, level }));
+    }
+    if let Some(message) = fields.fail {
+        let code = fields.code.unwrap_or(1);
+        if !(1..=255).contains(&code) {
+            return Err(String::from("'code' should be an exit code from 1 to 255"));
+        }
+        actions.push(Action::Fail(Failure {
+            message,
+            code: code as i32,
+        }));
+    }
+
+    if actions.len() != 1 {
+        return Err(String::from(
+            "an action needs exactly one of 'cmd', 'if', 'task', 'cd', 'confirm', 'script', \
+             'echo', 'fail', 'mkdir', 'rm', 'touch', 'copy', 'move', 'symlink', 'write' or \
+             'chmod'",
+        ));
+    }
+    let action = actions.remove(0);
+
+    if fields.shell.is_some() && !matches!(action, Action::Command(_) | Action::If(_)) {
+        return Err(String::from(
+            "'shell' can only be set on 'cmd' and 'if' actions",
+        ));
+    }
+
+    if fields.interpreter.is_some() && !matches!(action, Action::Script(_)) {
+        return Err(String::from(
+            "'interpreter' can only be set on 'script' actions",
+        ));
+    }
+
+    let transfer = matches!(
+        action,
+        Action::File(
+            FileAction::Copy { .. } | FileAction::Move { .. } | FileAction::Symlink { .. }
+        )
+    );
+    if fields.to.is_some() && !transfer {
+        return Err(String::from(
+            "'to' can only be set on 'copy', 'move' and 'symlink' actions",
+        ));
+    }
+
+    if fields.content.is_some() && !matches!(action, Action::File(FileAction::Write { .. })) {
+        return Err(String::from("'content' can only be set on 'write' actions"));
+    }
+
+    if fields.mode.is_some() && !matches!(action, Action::File(FileAction::Chmod { .. })) {
+        return Err(String::from("'mode' can only be set on 'chmod' actions"));
+    }
+
+    if fields.level.is_some() && !matches!(action, Action::Echo(_)) {
+        return Err(String::from("'level' can only be set on 'echo' actions"));
+    }
+
+    if fields.code.is_some() && !matches!(action, Action::Fail(_)) {
+        return Err(String::from("'code' can only be set on 'fail' actions"));
+    }
+
+    Ok(action)
+}
+
+/// File actions set in the fields, with the fields that complete them
+fn file_actions(fields: &RawActionFields) -> Result<Vec<FileAction>, String> {
+    let needs = |value: &Option<String>, action: &str, field: &str| match value {
+        Some(value) => Ok(value.clone()),
+        None => Err(format!("'{}' needs '{}'", action, field)),
+    };
+
+    let mut actions = vec![];
+    actions.extend(fields.mkdir.clone().map(FileAction::Mkdir));
+    actions.extend(fields.rm.clone().map(FileAction::Remove));
+    actions.extend(fields.touch.clone().map(FileAction::Touch));
+    if let Some(from) = &fields.copy {
+        let to = needs(&fields.to, "copy", "to")?;
+        actions.push(FileAction::Copy {
+            from: from.clone(),
+            to,
+        });
+    }
+    if let Some(from) = &fields.move_from {
+        let to = needs(&fields.to, "move", "to")?;
+        actions.push(FileAction::Move {
+            from: from.clone(),
+            to,
+        });
+    }
+    if let Some(target) = &fields.symlink {
+        let link = needs(&fields.to, "symlink", "to")?;
+        actions.push(FileAction::Symlink {
+            target: target.clone(),
+            link,
+        });
+    }
+    if let Some(path) = &fields.write {
+        let content = needs(&fields.content, "write", "content")?;
+        actions.push(FileAction::Write {
+            path: path.clone(),
+            content,
+        });
+    }
+    if let Some(path) = &fields.chmod {
+        let mode = match &fields.mode {
+            Some(RawValue::Int(mode)) => mode.to_string(),
+            Some(RawValue::String(mode)) => mode.clone(),
+            _ => return Err(String::from("'chmod' needs 'mode'")),
+        };
+        let Some(mode) = parse_mode(&mode) else {
+            return Err(format!(
+                "invalid mode '{}', expected an octal mode like \"755\"",
+                mode
+            ));
+        };
+
+        actions.push(FileAction::Chmod {
+            path: path.clone(),
+            mode,
+        });
+    }
+
+    Ok(actions)
+}
+
+fn requirement(raw: RawRequirement) -> Result<Requirement, String> {
+    let mut kinds = vec![];
+    kinds.extend(raw.tool.map(|name| RequirementKind::Tool {
+        name,
+        version: raw.version.clone(),
+    }));
+    kinds.extend(raw.env.map(RequirementKind::Env));
+    kinds.extend(raw.file.map(RequirementKind::File));
+
+    if kinds.len() != 1 {
+        return Err(String::from(
+            "a requirement needs exactly one of 'tool', 'env' or 'file'",
+        ));
+    }
+    let kind = kinds.remove(0);
+
+    match (&kind, &raw.version) {
+        (RequirementKind::Tool { .. }, Some(version)) => {
+            if let Err(e) = semver::VersionReq::parse(version) {
+                return Err(format!("invalid version requirement '{}': {}", version, e));
+            }
+        }
+        (_, Some(_)) => {
+            return Err(String::from("'version' can only be set on 'tool'"));
+        }
+        (_, None) => {}
+    }
+
+    Ok(Requirement {
+        kind,
+        hint: raw.hint,
+    })
+}
+
+fn variables(raw: IndexMap<String, RawVariable>) -> Result<Vec<Variable>, TaskFileReadError> {
+    raw.into_iter()
+        .map(|(name, variable)| {
+            let fields = match variable {
+                RawVariable::Declared(fields) => *fields,
+                // A map that is not a declaration most likely has a misspelled field
+                RawVariable::Value(RawValue::Map(_)) => {
+                    return Err(TaskFileReadError::Structured(format!(
+                        "Variable '{}': unknown or invalid fields. Map values are written as \
+                         'value: {{ ... }}'",
+                        name
+                    )))
+                }
+                RawVariable::Value(value) => {
+                    return Ok(Variable {
+                        name,
+                        value: VariableValue::Static(value.into()),
+                        constraints: Constraints::default(),
+                        cache: None,
+                        secret: false,
+                        source: None,
+                    })
+                }
+            };
+
+            declared_variable(&name, fields).map_err(|message| {
+                TaskFileReadError::Structured(format!("Variable '{}': {}", name, message))
+            })
+        })
+        .collect()
+}
+
+fn declared_variable(name: &str, fields: RawVariableFields) -> Result<Variable, String> {
+    let mut value_type = match fields.value_type.as_deref() {
+        None => None,
+        Some(type_name) => match VariableType::from_name(type_name) {
+            Some(value_type) => Some(value_type),
+            None => {
+                return Err(format!(
+                    "unknown type '{}', expected one of: {}",
+                    type_name,
+                    VariableType::NAMES.join(", ")
+                ))
+            }
+        },
+    };
+
+    if fields.shell.is_some() && fields.cmd.is_none() && fields.from_cmd_json.is_none() {
+        return Err(String::from(
+            "'shell' can only be set together with 'cmd' or 'from_cmd_json'",
+        ));
+    }
+
+    let cache = match fields.cache.as_deref() {
+        None => None,
+        Some(_) if fields.cmd.is_none() && fields.from_cmd_json.is_none() => {
+            return Err(String::from(
+                "'cache' can only be set together with 'cmd' or 'from_cmd_json'",
+            ))
+        }
+        Some(text) => match parse_duration(text) {
+            Some(cache) => Some(cache),
+            None => {
+                return Err(format!(
+                    "'cache' should be a duration like 1h, got '{}'",
+                    text
+                ))
+            }
+        },
+    };
+
+    if fields.from_cmd_json.is_some() {
+        if value_type.is_some_and(|t| t != VariableType::Json) {
+            return Err(String::from(
+                "'from_cmd_json' reads JSON and cannot have another 'type'",
+            ));
+        }
+        value_type = Some(VariableType::Json);
+    }
+
+    let command = |command| {
+        VariableValue::Action(Action::Command(ActionCommand {
+            command,
+            shell: fields.shell.clone(),
+            tty: false,
+        }))
+    };
+
+    let mut values = vec![];
+    values.extend(
+        fields
+            .value
+            .map(|value| VariableValue::Static(value.into())),
+    );
+    values.extend(fields.cmd.map(command));
+    values.extend(fields.from_cmd_json.map(command));
+    values.extend(fields.from_file.map(VariableValue::File));
+    values.extend(fields.from_env.map(VariableValue::Env));
+
+    let prompts = [
+        (PromptKind::Text, fields.prompt),
+        (PromptKind::Password, fields.password),
+        (PromptKind::Select, fields.select),
+        (PromptKind::MultiSelect, fields.multiselect),
+    ];
+    for (kind, message) in prompts {
+        let Some(message) = message else {
+            continue;
+        };
+
+        if kind != PromptKind::Text && fields.default.is_some() {
+            return Err(format!(
+                "'default' cannot be set together with '{}'",
+                kind.name()
+            ));
+        }
+        if matches!(kind, PromptKind::Select | PromptKind::MultiSelect) && fields.choices.is_empty()
+        {
+            return Err(format!("'{}' needs an 'enum' to choose from", kind.name()));
+        }
+
+        values.push(VariableValue::Prompt(Prompt {
+            kind,
+            message,
+            default: fields.default.clone(),
+        }));
+    }
+    if fields.default.is_some() && !values.iter().any(|v| matches!(v, VariableValue::Prompt(_))) {
+        return Err(String::from(
+            "'default' can only be set together with 'prompt'",
+        ));
+    }
+
+    let value =
+        match values.len() {
+            0 if fields.required => VariableValue::Static(Value::Null),
+            0 => return Err(String::from(
+                "a variable needs a 'value', 'cmd', 'from_file', 'from_env', 'from_cmd_json' or \
+                 a prompt",
+            )),
+            1 => values.remove(0),
+            _ => {
+                return Err(String::from(
+                    "a variable can only have one of 'value', 'cmd', 'from_file', 'from_env', \
+                 'from_cmd_json' and a prompt",
+                ))
+            }
+        };
+
+    // Passwords are always secret
+    let secret = fields.secret
+        || matches!(&value, VariableValue::Prompt(prompt) if prompt.kind == PromptKind::Password);
+    if secret && cache.is_some() {
+        return Err(String::from("a secret variable cannot be cached on disk"));
+    }
+
+    Ok(Variable {
+        name: String::from(name),
+        value,
+        constraints: Constraints {
+            value_type,
+            required: fields.required,
+            choices: fields.choices.into_iter().map(Value::from).collect(),
+            pattern: fields.pattern,
+        },
+        cache,
+        secret,
+        source: None,
+    })
+}
+
+impl From<RawValue> for Value {
+    fn from(value: RawValue) -> Self {
+        match value {
+            RawValue::Bool(b) => Value::Bool(b),
+            RawValue::Int(i) => Value::Int(i),
+            RawValue::Float(f) => Value::Float(f),
+            RawValue::String(s) => Value::String(s),
+            RawValue::List(items) => Value::List(items.into_iter().map(Value::from).collect()),
+            RawValue::Map(map) => Value::Map(
+                map.into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+            RawValue::Null(()) => Value::Null,
+        }
+    }
+}
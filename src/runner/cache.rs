@@ -0,0 +1,130 @@
+//! On-disk cache of command variable output, for variables declared with `cache="1h"`.
+//!
+//! Entries live in `$XDG_CACHE_HOME/jatr` (`~/.cache/jatr` by default), one JSON file per
+//! command, shell and working directory. Files are named by a SHA-256 digest of those, so
+//! entries stay valid across jatr builds.
+
+use super::executor::ExecuteRequest;
+use camino::Utf8PathBuf;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    command: String,
+    shell: String,
+    work_dir: String,
+    /// Seconds since the Unix epoch the output was stored at
+    created: u64,
+    stdout: String,
+}
+
+fn dir() -> Option<Utf8PathBuf> {
+    let base = match env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => Utf8PathBuf::from(dir),
+        _ => Utf8PathBuf::from(env::var("HOME").ok()?).join(".cache"),
+    };
+
+    Some(base.join("jatr"))
+}
+
+fn path(request: &ExecuteRequest) -> Option<Utf8PathBuf> {
+    let mut hasher = Sha256::new();
+    for part in [&request.command, &request.shell, &request.work_dir] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+
+    Some(dir()?.join(format!("{:x}.json", hasher.finalize())))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Output of the request stored less than `ttl` ago
+pub fn load(request: &ExecuteRequest, ttl: Duration) -> Option<String> {
+    let content = fs::read_to_string(path(request)?).ok()?;
+    let entry: Entry = serde_json::from_str(&content).ok()?;
+
+    let matches = entry.command == request.command
+        && entry.shell == request.shell
+        && entry.work_dir == request.work_dir;
+    let fresh = now().saturating_sub(entry.created) < ttl.as_secs();
+
+    match matches && fresh {
+        true => Some(entry.stdout),
+        false => None,
+    }
+}
+
+/// Stores the output of a request. Failing to write the cache only costs a rerun, so errors
+/// are logged and otherwise ignored
+pub fn store(request: &ExecuteRequest, stdout: &str) {
+    let Some(path) = path(request) else {
+        return;
+    };
+
+    let entry = Entry {
+        command: request.command.clone(),
+        shell: request.shell.clone(),
+        work_dir: request.work_dir.clone(),
+        created: now(),
+        stdout: String::from(stdout),
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, serde_json::to_string(&entry).unwrap_or_default()));
+
+    if let Err(e) = result {
+        debug!("Cannot write cache entry {}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(work_dir: &str) -> ExecuteRequest {
+        ExecuteRequest {
+            command: String::from("git describe --tags"),
+            shell: String::from("sh"),
+            work_dir: String::from(work_dir),
+            tty: false,
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        env::set_var("XDG_CACHE_HOME", dir.path());
+        let hour = Duration::from_secs(3600);
+
+        store(&request("/srv/app"), "v1.2.0");
+
+        assert_eq!(load(&request("/srv/app"), hour).as_deref(), Some("v1.2.0"));
+        assert_eq!(load(&request("/srv/app"), Duration::ZERO), None);
+        assert_eq!(load(&request("/srv/other"), hour), None);
+
+        let stale = Entry {
+            command: String::from("git describe --tags"),
+            shell: String::from("sh"),
+            work_dir: String::from("/srv/app"),
+            created: now() - 2 * hour.as_secs(),
+            stdout: String::from("v1.1.0"),
+        };
+        let path = path(&request("/srv/app")).unwrap();
+        fs::write(&path, serde_json::to_string(&stale).unwrap()).unwrap();
+
+        assert_eq!(load(&request("/srv/app"), hour), None);
+        assert!(path.starts_with(dir.path()));
+        assert_eq!(path.file_stem().unwrap().len(), 64);
+    }
+}
@@ -1,23 +1,23 @@
 use crate::runner::Result;
 use crate::S;
 use camino::Utf8Path;
-use log::debug;
-use std::process::{Command, ExitStatus};
+use indexmap::IndexMap;
+use std::env;
+use std::io::{self, IsTerminal};
 
-#[derive(Debug, Default)]
-pub struct ExecuteResult {
-    pub cmd: String,
-    pub exit_status: ExitStatus,
-    pub stdout: String,
-
-    #[allow(dead_code)]
-    pub stderr: String,
-}
+/// Prefix of environment variables that override task file variables
+pub const OVERRIDE_PREFIX: &str = "JATR_VAR_";
 
+/// Shell and working directory commands are executed with, and the variable values given
+/// from outside the task file
 #[derive(Debug, Clone)]
 pub struct RunnerEnvironment {
     default_shell: String,
     working_dir: String,
+    task_file_dir: String,
+    overrides: IndexMap<String, String>,
+    args: Vec<String>,
+    interactive: bool,
 }
 
 impl Default for RunnerEnvironment {
@@ -25,52 +25,16 @@ impl Default for RunnerEnvironment {
         Self {
             default_shell: S!("sh"),
             working_dir: String::from("."),
+            task_file_dir: String::from("."),
+            overrides: IndexMap::new(),
+            args: vec![],
+            interactive: true,
         }
     }
 }
 
 impl RunnerEnvironment {
-    pub fn execute(
-        &self,
-        shell_command: &str,
-        shell: Option<&str>,
-        tty: bool,
-    ) -> Result<ExecuteResult> {
-        let mut cmd = self.create_cmd(shell, shell_command);
-
-        let output = match tty {
-            true => cmd.spawn()?.wait_with_output()?,
-            false => cmd.output()?,
-        };
-
-        debug!(
-            "Executed: {}. Exit Code: {}. Stdout: '{}' Stderr: '{}'",
-            shell_command,
-            output.status,
-            String::from_utf8(output.stdout.clone()).unwrap(),
-            String::from_utf8(output.stderr.clone()).unwrap()
-        );
-
-        Ok(ExecuteResult {
-            cmd: String::from(shell_command),
-            exit_status: output.status,
-            stdout: String::from_utf8(output.stdout).unwrap(),
-            stderr: String::from_utf8(output.stderr).unwrap(),
-        })
-    }
-
-    fn create_cmd(&self, shell: Option<&str>, shell_command: &str) -> Command {
-        let mut cmd = Command::new(match shell {
-            Some(s) => String::from(s),
-            None => self.default_shell.clone(),
-        });
-
-        cmd.current_dir(self.working_dir.clone());
-        cmd.arg("-c").arg(shell_command);
-
-        cmd
-    }
-
+    /// Changes the working directory, resolving `dir` relative to the current one
     pub fn work_dir(&mut self, dir: &str) -> Result<()> {
         let path = Utf8Path::from_path(self.working_dir.as_ref()).unwrap();
         let joined_path = path.join(dir);
@@ -83,4 +47,56 @@ impl RunnerEnvironment {
     pub fn get_work_dir(&self) -> String {
         self.working_dir.clone()
     }
+
+    pub fn task_file_dir(&mut self, dir: &str) {
+        self.task_file_dir = String::from(dir);
+    }
+
+    pub fn get_task_file_dir(&self) -> String {
+        self.task_file_dir.clone()
+    }
+
+    /// Arguments passed through to templates as `args`
+    pub fn args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn get_args(&self) -> Vec<String> {
+        self.args.clone()
+    }
+
+    /// Allows asking the user for prompt variables and confirmations, like the absence of
+    /// `--non-interactive`
+    pub fn interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    /// Questions can be asked: interactivity is allowed and stdin is a terminal
+    pub fn is_interactive(&self) -> bool {
+        self.interactive && io::stdin().is_terminal()
+    }
+
+    pub fn default_shell(&mut self, shell: &str) {
+        self.default_shell = String::from(shell);
+    }
+
+    pub fn get_default_shell(&self) -> String {
+        self.default_shell.clone()
+    }
+
+    /// Sets a variable to `value` regardless of how the task file defines it, like
+    /// `--var name=value` on the command line
+    pub fn override_variable(&mut self, name: &str, value: &str) {
+        self.overrides
+            .insert(String::from(name), String::from(value));
+    }
+
+    /// Value overriding a variable: one set with [`Self::override_variable`], else the
+    /// `JATR_VAR_<name>` environment variable
+    pub fn get_override(&self, name: &str) -> Option<String> {
+        match self.overrides.get(name) {
+            Some(value) => Some(value.clone()),
+            None => env::var(format!("{}{}", OVERRIDE_PREFIX, name)).ok(),
+        }
+    }
 }
@@ -0,0 +1,126 @@
+use crate::runner::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Command the runner wants executed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecuteRequest {
+    pub command: String,
+    pub shell: String,
+    pub work_dir: String,
+    pub tty: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecuteResult {
+    pub cmd: String,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ExecuteResult {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Backend the runner executes commands with
+pub trait Executor {
+    fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResult>;
+}
+
+/// Default executor, runs commands as `<shell> -c <command>` child processes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessExecutor;
+
+impl Executor for ProcessExecutor {
+    fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResult> {
+        let mut cmd = Command::new(&request.shell);
+        cmd.current_dir(&request.work_dir);
+        cmd.arg("-c").arg(&request.command);
+
+        let output = match request.tty {
+            true => cmd.spawn()?.wait_with_output()?,
+            false => cmd.output()?,
+        };
+
+        Ok(ExecuteResult {
+            cmd: request.command.clone(),
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Executor that records requests instead of running them, for testing tasks and the runner.
+/// Commands succeed with empty output unless a response was registered for them.
+///
+/// ```
+/// use jatr::runner::executor::{ExecuteResult, RecordingExecutor};
+/// use jatr::{reader, Runner, RunnerEnvironment, RunnerResult};
+///
+/// let task_file = reader::read(String::from(
+///     r#"tasks { build { if "test -f Cargo.toml"; - "cargo build" } }"#,
+/// ))
+/// .unwrap();
+///
+/// let executor = RecordingExecutor::default().respond("test -f Cargo.toml", 1, "");
+/// let mut runner =
+///     Runner::with_executor(&task_file, RunnerEnvironment::default(), &executor);
+///
+/// let result = runner.run(task_file.find_task("build").unwrap()).unwrap();
+///
+/// assert_eq!(result, RunnerResult::Skipped);
+/// assert_eq!(executor.commands(), vec!["test -f Cargo.toml"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    responses: HashMap<String, ExecuteResult>,
+    requests: RefCell<Vec<ExecuteRequest>>,
+}
+
+impl RecordingExecutor {
+    /// Registers the exit code and stdout returned when `command` is executed
+    pub fn respond(mut self, command: &str, exit_code: i32, stdout: &str) -> Self {
+        self.responses.insert(
+            String::from(command),
+            ExecuteResult {
+                cmd: String::from(command),
+                exit_code,
+                stdout: String::from(stdout),
+                stderr: String::new(),
+            },
+        );
+
+        self
+    }
+
+    pub fn requests(&self) -> Vec<ExecuteRequest> {
+        self.requests.borrow().clone()
+    }
+
+    pub fn commands(&self) -> Vec<String> {
+        self.requests
+            .borrow()
+            .iter()
+            .map(|r| r.command.clone())
+            .collect()
+    }
+}
+
+impl Executor for RecordingExecutor {
+    fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResult> {
+        self.requests.borrow_mut().push(request.clone());
+
+        Ok(match self.responses.get(&request.command) {
+            Some(response) => response.clone(),
+            None => ExecuteResult {
+                cmd: request.command.clone(),
+                ..Default::default()
+            },
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! File actions, done with the standard library so they work the same on every platform.
+
+use crate::tasks::FileAction;
+use camino::Utf8Path;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Applies a file action whose templates are rendered. Relative paths start at `work_dir`.
+///
+/// Empty paths are rejected, as are `rm`, `move` and `copy` targets that are the working
+/// directory or contain it, like `.`, `..` or `/`, which a mistyped template easily renders
+pub fn apply(action: &FileAction, work_dir: &Utf8Path) -> io::Result<()> {
+    let work_dir = fs::canonicalize(work_dir).unwrap_or_else(|_| work_dir.into());
+    let path = |path: &str| match path.trim().is_empty() {
+        true => Err(invalid(String::from("path is empty"))),
+        false => Ok(normalize(&work_dir.join(path))),
+    };
+    let target = |target: &str| {
+        let path = path(target)?;
+        match work_dir.starts_with(resolve(&path)) {
+            true => Err(invalid(format!(
+                "'{}' is the working directory or contains it",
+                target
+            ))),
+            false => Ok(path),
+        }
+    };
+
+    match action {
+        FileAction::Mkdir(dir) => fs::create_dir_all(path(dir)?),
+        FileAction::Remove(path) => remove(&target(path)?),
+        FileAction::Touch(file) => touch(&path(file)?),
+        FileAction::Copy { from, to } => copy(&path(from)?, &target(to)?),
+        FileAction::Move { from, to } => {
+            let (from, to) = (target(from)?, target(to)?);
+            // Renaming fails across filesystems, where the tree is copied instead
+            fs::rename(&from, &to).or_else(|_| copy(&from, &to).and_then(|_| remove(&from)))
+        }
+        FileAction::Symlink { target, link } => {
+            if target.trim().is_empty() {
+                return Err(invalid(String::from("symlink target is empty")));
+            }
+            symlink(Path::new(target), &path(link)?)
+        }
+        FileAction::Write {
+            path: file,
+            content,
+        } => {
+            let file = path(file)?;
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file, content)
+        }
+        FileAction::Chmod { path: file, mode } => chmod(&path(file)?, *mode),
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, message)
+}
+
+/// `path` with `.` and `..` components resolved without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Real location of an existing path. Symlinks stay as they are, removing or replacing one
+/// leaves what it points to alone
+fn resolve(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.is_symlink() => {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn touch(path: &Path) -> io::Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy(&entry.path(), &to.join(entry.file_name()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().unwrap_or(link).join(target);
+    match resolved.is_dir() {
+        true => std::os::windows::fs::symlink_dir(target, link),
+        false => std::os::windows::fs::symlink_file(target, link),
+    }
+}
+
+#[cfg(unix)]
+fn chmod(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn chmod(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+
+    fs::set_permissions(path, permissions)
+}
@@ -0,0 +1,155 @@
+//! Functions and filters available to templates on top of the ones Tera ships with.
+//!
+//! Functions take named arguments, like every Tera function:
+//!
+//! * `env(name="HOME", default="")` reads an environment variable
+//! * `read_file(path="VERSION")` returns the content of a file
+//! * `exists(path="Cargo.lock")` is true when a file or directory exists
+//! * `glob(pattern="src/**/*.rs")` lists matching files, sorted
+//! * `which(name="docker")` is the path of an executable in `PATH`, or null
+//!
+//! Filters:
+//!
+//! * `shell_quote` quotes a value for POSIX shells
+//! * `sha256` is the hex digest of a string
+//! * `semver_bump(part="minor")` increments a version, `patch` by default
+//!
+//! Relative paths are resolved against the working directory of the task.
+
+use crate::utils::shell_quote;
+use camino::{Utf8Path, Utf8PathBuf};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::{env, fs};
+use tera::{Error, Result, Tera, Value};
+
+/// Registers the functions and filters, resolving relative paths against `work_dir`
+pub fn register(tera: &mut Tera, work_dir: &Utf8Path) {
+    let dir = work_dir.to_path_buf();
+    tera.register_function("read_file", move |args: &HashMap<String, Value>| {
+        let path = dir.join(string_arg("read_file", args, "path")?);
+        fs::read_to_string(&path)
+            .map(Value::String)
+            .map_err(|e| Error::msg(format!("Cannot read '{}': {}", path, e)))
+    });
+
+    let dir = work_dir.to_path_buf();
+    tera.register_function("exists", move |args: &HashMap<String, Value>| {
+        let path = dir.join(string_arg("exists", args, "path")?);
+        Ok(Value::Bool(path.exists()))
+    });
+
+    let dir = work_dir.to_path_buf();
+    tera.register_function("glob", move |args: &HashMap<String, Value>| {
+        glob(&dir, &string_arg("glob", args, "pattern")?)
+    });
+
+    tera.register_function("env", env_function);
+    tera.register_function(
+        "which",
+        |args: &HashMap<String, Value>| match which::which(string_arg("which", args, "name")?) {
+            Ok(path) => Ok(Value::String(path.to_string_lossy().into_owned())),
+            Err(_) => Ok(Value::Null),
+        },
+    );
+
+    tera.register_filter(
+        "shell_quote",
+        |value: &Value, _: &HashMap<String, Value>| Ok(Value::String(shell_quote(&plain(value)))),
+    );
+    tera.register_filter("sha256", |value: &Value, _: &HashMap<String, Value>| {
+        Ok(Value::String(format!("{:x}", Sha256::digest(plain(value)))))
+    });
+    tera.register_filter("semver_bump", semver_bump);
+}
+
+fn string_arg(function: &str, args: &HashMap<String, Value>, name: &str) -> Result<String> {
+    match args.get(name) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(value) => Err(Error::msg(format!(
+            "Argument '{}' of '{}' should be a string, got {}",
+            name, function, value
+        ))),
+        None => Err(Error::msg(format!(
+            "Function '{}' is missing argument '{}'",
+            function, name
+        ))),
+    }
+}
+
+/// Value as text, strings without their quotes
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn env_function(args: &HashMap<String, Value>) -> Result<Value> {
+    let name = string_arg("env", args, "name")?;
+
+    match (env::var(&name), args.get("default")) {
+        (Ok(value), _) => Ok(Value::String(value)),
+        (Err(_), Some(default)) => Ok(default.clone()),
+        (Err(_), None) => Err(Error::msg(format!(
+            "Environment variable '{}' is not set and has no default",
+            name
+        ))),
+    }
+}
+
+fn glob(dir: &Utf8Path, pattern: &str) -> Result<Value> {
+    let walker = globwalk::GlobWalkerBuilder::from_patterns(dir, &[pattern])
+        .build()
+        .map_err(|e| Error::msg(format!("Invalid glob '{}': {}", pattern, e)))?;
+
+    let mut paths: Vec<String> = walker
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.into_path()).ok())
+        .map(|path| match path.strip_prefix(dir) {
+            Ok(relative) => relative.to_string(),
+            Err(_) => path.to_string(),
+        })
+        .collect();
+    paths.sort();
+
+    Ok(Value::Array(paths.into_iter().map(Value::String).collect()))
+}
+
+fn semver_bump(value: &Value, args: &HashMap<String, Value>) -> Result<Value> {
+    let text = plain(value);
+    let prefix = match text.starts_with('v') {
+        true => "v",
+        false => "",
+    };
+    let mut version = semver::Version::parse(&text[prefix.len()..])
+        .map_err(|e| Error::msg(format!("Cannot bump '{}': {}", text, e)))?;
+
+    let part = match args.get("part") {
+        Some(part) => plain(part),
+        None => String::from("patch"),
+    };
+
+    match part.as_str() {
+        "major" => {
+            version.major += 1;
+            version.minor = 0;
+            version.patch = 0;
+        }
+        "minor" => {
+            version.minor += 1;
+            version.patch = 0;
+        }
+        "patch" => version.patch += 1,
+        part => {
+            return Err(Error::msg(format!(
+                "Unknown version part '{}', expected major, minor or patch",
+                part
+            )))
+        }
+    }
+    version.pre = semver::Prerelease::EMPTY;
+    version.build = semver::BuildMetadata::EMPTY;
+
+    Ok(Value::String(format!("{}{}", prefix, version)))
+}
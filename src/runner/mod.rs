@@ -1,29 +1,57 @@
+mod cache;
 pub mod environment;
+pub mod executor;
+mod files;
+mod functions;
 mod output;
+mod prompt;
+pub mod redact;
+mod requirements;
+mod script;
 pub mod templating;
-
-use crate::tasks::{Action, ActionCommand, Task, TaskFile, Value, Variable, VariableValue};
-use environment::{ExecuteResult, RunnerEnvironment};
+pub mod typing;
+
+use crate::tasks::{
+    Action, ActionCommand, ActionScript, FileAction, Prompt, PromptKind, Task, TaskFile, Value,
+    Variable, VariableValue,
+};
+use crate::utils::shell_quote;
+use camino::Utf8Path;
+use environment::RunnerEnvironment;
+use executor::{ExecuteRequest, ExecuteResult, Executor, ProcessExecutor};
+use indexmap::IndexMap;
+use inquire::InquireError;
 use log::{debug, error};
 use output::Output;
-use std::result;
+use redact::Secrets;
+use script::ScriptFile;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fmt, result};
 use templating::Templating;
 
 pub type Result<T> = result::Result<T, RunnerError>;
 
+/// Error that aborted a task run, as opposed to a task that ran and failed
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct RunnerError(String, RunnerErrorData);
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum RunnerErrorData {
     TemplateError(tera::Error),
     VariableResolveError { variable: String },
+    InvalidVariable { variable: String },
     Io(std::io::Error),
     TaskNotFound,
+    InputRequired,
+    Aborted,
+    Failed { code: i32 },
+    MissingRequirements,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RunnerResult {
     Success,
     Skipped,
@@ -37,34 +65,69 @@ pub struct ActionResult {
     failed: bool,
 }
 
+/// Resolved variables keyed by the task that defines them, `None` for global variables
+type Memo = HashMap<(Option<String>, String), Value>;
+
+/// Runs the tasks of a single task file
 pub struct Runner<'a> {
     task_file: &'a TaskFile,
     templating: Templating,
     environment: RunnerEnvironment,
+    executor: &'a dyn Executor,
     output: Output,
+    /// Shared with the runners of called tasks so each variable is resolved once per run
+    memo: Rc<RefCell<Memo>>,
+    /// Values of resolved secret variables, masked in output, logs and errors
+    secrets: Rc<RefCell<Secrets>>,
 }
 
 impl<'a> Runner<'a> {
     pub fn for_taskfile(task_file: &'a TaskFile, environment: RunnerEnvironment) -> Self {
+        Self::with_executor(task_file, environment, &ProcessExecutor)
+    }
+
+    /// Creates a runner that executes commands with `executor` instead of spawning processes
+    pub fn with_executor(
+        task_file: &'a TaskFile,
+        environment: RunnerEnvironment,
+        executor: &'a dyn Executor,
+    ) -> Self {
+        let secrets = Rc::default();
+
         Self {
             task_file,
             templating: Templating::default(),
             environment,
-            output: Output::for_task(""),
+            executor,
+            output: Output::for_task("", Rc::clone(&secrets)),
+            memo: Rc::default(),
+            secrets,
         }
     }
 
+    /// Checks the requirements of the task and the tasks it runs, then resolves the task's
+    /// variables and runs its actions in order. Values of secret variables are masked in the
+    /// returned error
     pub fn run(&mut self, task: &Task) -> Result<RunnerResult> {
-        self.output = Output::for_task(task.name.as_str());
+        self.check_requirements(task)
+            .and_then(|_| self.run_task(task))
+            .map_err(|RunnerError(message, data)| RunnerError(self.redact(&message), data))
+    }
+
+    fn run_task(&mut self, task: &Task) -> Result<RunnerResult> {
+        self.output = Output::for_task(task.name.as_str(), Rc::clone(&self.secrets));
 
         debug!("Running task: {}", task.name);
         self.resolve_variables(task)?;
 
         for action in task.actions.iter() {
-            let action_result = self.run_action(action, false)?;
+            let action_result = self.run_action(task, action)?;
 
             if action_result.failed {
-                error!("Action failed: {:?}", action_result);
+                error!(
+                    "Action failed: {}",
+                    self.redact(&format!("{:?}", action_result.last_command))
+                );
                 return Ok(RunnerResult::Failure);
             }
 
@@ -76,11 +139,11 @@ impl<'a> Runner<'a> {
         Ok(RunnerResult::Success)
     }
 
-    fn run_action(&mut self, action: &Action, silent: bool) -> Result<ActionResult> {
+    fn run_action(&mut self, task: &Task, action: &Action) -> Result<ActionResult> {
         match action {
             Action::Command(cmd) => {
-                let result = self.run_action_command(cmd, action, silent)?;
-                let break_execution = !result.exit_status.success();
+                let result = self.run_action_command(cmd, action)?;
+                let break_execution = !result.success();
 
                 return Ok(ActionResult {
                     last_command: result,
@@ -89,8 +152,8 @@ impl<'a> Runner<'a> {
                 });
             }
             Action::If(cmd) => {
-                let result = self.run_action_command(cmd, action, silent)?;
-                let mut break_execution = !result.exit_status.success();
+                let result = self.run_action_command(cmd, action)?;
+                let mut break_execution = !result.success();
 
                 if !break_execution && result.stdout.trim() == "false" {
                     break_execution = true;
@@ -106,15 +169,19 @@ impl<'a> Runner<'a> {
                 });
             }
             Action::Task(call) => {
-                let Some(task) = self.task_file.tasks.get(call.name.as_str()) else {
+                let Some(task) = self.task_file.find_task(call.name.as_str()) else {
                     return Err(RunnerError(
                         format!("Task '{}' not found", call.name),
                         RunnerErrorData::TaskNotFound,
                     ));
                 };
 
-                let mut runner = Runner::for_taskfile(self.task_file, self.environment.clone());
-                let result = runner.run(task)?;
+                let mut runner =
+                    Runner::with_executor(self.task_file, self.environment.clone(), self.executor);
+                runner.memo = Rc::clone(&self.memo);
+                runner.secrets = Rc::clone(&self.secrets);
+                // Its requirements were checked with those of the task that started the run
+                let result = runner.run_task(task)?;
                 let failed = matches!(result, RunnerResult::Failure);
 
                 return Ok(ActionResult {
@@ -123,9 +190,68 @@ impl<'a> Runner<'a> {
                     failed,
                 });
             }
+            Action::Script(script) => {
+                let result = self.run_action_script(script)?;
+                let break_execution = !result.success();
+
+                return Ok(ActionResult {
+                    last_command: result,
+                    break_execution,
+                    failed: break_execution,
+                });
+            }
+            Action::File(action) => {
+                let action = self.template_file_action(action)?;
+                self.output.file_execution(&action);
+
+                let work_dir = self.environment.get_work_dir();
+                files::apply(&action, Utf8Path::new(&work_dir)).map_err(|e| {
+                    RunnerError(
+                        format!(
+                            "{} '{}' failed: {}",
+                            action.name(),
+                            action.templates()[0],
+                            e
+                        ),
+                        RunnerErrorData::Io(e),
+                    )
+                })?;
+            }
+            Action::Echo(message) => {
+                let text = self.templating.process(&message.text)?;
+                self.output.echo(message.level, &text);
+            }
+            Action::Fail(failure) => {
+                let message = self.templating.process(&failure.message)?;
+                self.output.fail(&message);
+
+                return Err(RunnerError(
+                    message,
+                    RunnerErrorData::Failed { code: failure.code },
+                ));
+            }
             Action::Cd(s) => {
-                self.environment.work_dir(&s)?;
-                self.output.cd_execution(self.environment.get_work_dir().as_str());
+                self.environment.work_dir(s)?;
+
+                let work_dir = self.environment.get_work_dir();
+                self.templating.work_dir(&work_dir);
+
+                // A task file variable named `cwd` takes precedence over the built-in
+                let mut defined = self.task_file.variables.iter().chain(&task.variables);
+                if !defined.any(|var| var.name == "cwd") {
+                    self.templating
+                        .add_variable("cwd", Value::String(work_dir.clone()));
+                }
+                self.output.cd_execution(work_dir.as_str());
+            }
+            Action::Confirm(message) => {
+                let message = self.templating.process(message)?;
+                if !self.confirm(&message)? {
+                    return Err(RunnerError(
+                        format!("Aborted, '{}' was not confirmed", message),
+                        RunnerErrorData::Aborted,
+                    ));
+                }
             }
             Action::Noop => {}
         }
@@ -137,60 +263,503 @@ impl<'a> Runner<'a> {
         })
     }
 
-    fn run_action_command(
-        &self,
-        cmd: &ActionCommand,
-        action: &Action,
-        silent: bool,
-    ) -> Result<ExecuteResult> {
-        let templated_command = self.templating.process(&cmd.command)?;
+    fn run_action_command(&self, cmd: &ActionCommand, action: &Action) -> Result<ExecuteResult> {
+        let request = self.command_request(cmd)?;
 
-        if !silent {
-            if let Action::Command(_) = action {
-                self.output.cmd_execution(&templated_command);
-            }
+        if let Action::Command(_) = action {
+            self.output.cmd_execution(&request.command);
         }
 
-        let result = self
-            .environment
-            .execute(templated_command.as_str(), None, cmd.tty)?;
+        self.execute(&request)
+    }
+
+    fn run_action_script(&self, script: &ActionScript) -> Result<ExecuteResult> {
+        let body = self.templating.process(&script.body)?;
+        let default_shell = self.environment.get_default_shell();
+        let file = ScriptFile::create(script, &body, &default_shell, self.output.task_name())?;
+
+        self.output.script_execution(&file.interpreter);
+        self.execute(&ExecuteRequest {
+            command: file.command.clone(),
+            shell: default_shell,
+            work_dir: self.environment.get_work_dir(),
+            tty: true,
+        })
+    }
+
+    fn template_file_action(&self, action: &FileAction) -> Result<FileAction> {
+        let process = |template: &String| self.templating.process(template);
+
+        Ok(match action {
+            FileAction::Mkdir(path) => FileAction::Mkdir(process(path)?),
+            FileAction::Remove(path) => FileAction::Remove(process(path)?),
+            FileAction::Touch(path) => FileAction::Touch(process(path)?),
+            FileAction::Copy { from, to } => FileAction::Copy {
+                from: process(from)?,
+                to: process(to)?,
+            },
+            FileAction::Move { from, to } => FileAction::Move {
+                from: process(from)?,
+                to: process(to)?,
+            },
+            FileAction::Symlink { target, link } => FileAction::Symlink {
+                target: process(target)?,
+                link: process(link)?,
+            },
+            FileAction::Write { path, content } => FileAction::Write {
+                path: process(path)?,
+                content: process(content)?,
+            },
+            FileAction::Chmod { path, mode } => FileAction::Chmod {
+                path: process(path)?,
+                mode: *mode,
+            },
+        })
+    }
+
+    fn execute(&self, request: &ExecuteRequest) -> Result<ExecuteResult> {
+        let result = self.executor.execute(request)?;
+        self.log_execution(request, &result);
 
         Ok(result)
     }
 
-    fn resolve_variables(&mut self, task: &Task) -> Result<()> {
-        for var in self.task_file.variables.iter() {
-            debug!("Resolving global ariable: {:?}", var);
+    fn log_execution(&self, request: &ExecuteRequest, result: &ExecuteResult) {
+        debug!(
+            "Executed: {}. Exit Code: {}. Stdout: '{}' Stderr: '{}'",
+            self.redact(&request.command),
+            result.exit_code,
+            self.redact(&result.stdout),
+            self.redact(&result.stderr)
+        );
+    }
 
-            let value = self.resolve_variable(var)?;
-            self.templating.add_variable(var.name.as_str(), value);
+    fn redact(&self, text: &str) -> String {
+        self.secrets.borrow().redact(text)
+    }
+
+    /// The task followed by the tasks it runs through `task` actions, directly or not, each
+    /// once. Unknown tasks are left to fail when they are run
+    fn called_tasks<'t>(&'t self, task: &'t Task) -> Vec<&'t Task> {
+        let mut called = vec![task];
+        let mut index = 0;
+
+        while let Some(current) = called.get(index) {
+            for action in &current.actions {
+                let Action::Task(call) = action else {
+                    continue;
+                };
+                let Some(next) = self.task_file.find_task(&call.name) else {
+                    continue;
+                };
+
+                if !called.iter().any(|seen| std::ptr::eq(*seen, next)) {
+                    called.push(next);
+                }
+            }
+            index += 1;
+        }
+
+        called
+    }
+
+    fn command_request(&self, cmd: &ActionCommand) -> Result<ExecuteRequest> {
+        Ok(ExecuteRequest {
+            command: self.templating.process(&cmd.command)?,
+            shell: cmd
+                .shell
+                .clone()
+                .unwrap_or_else(|| self.environment.get_default_shell()),
+            work_dir: self.environment.get_work_dir(),
+            tty: cmd.tty,
+        })
+    }
+
+    /// Checks the requirements of the task and of every task it runs through `task` actions,
+    /// reporting every one not met in a single error before anything runs
+    fn check_requirements(&self, task: &Task) -> Result<()> {
+        let work_dir = self.environment.get_work_dir();
+        let mut report: Vec<String> = vec![];
+
+        for called in self.called_tasks(task) {
+            let problems =
+                requirements::check(&called.requirements, Utf8Path::new(&work_dir), |tool| {
+                    let result = self.execute(&ExecuteRequest {
+                        command: format!("{} --version", shell_quote(tool)),
+                        shell: self.environment.get_default_shell(),
+                        work_dir: work_dir.clone(),
+                        tty: false,
+                    })?;
+
+                    Ok(format!("{}{}", result.stdout, result.stderr))
+                })?;
+
+            for problem in problems {
+                report.push(match std::ptr::eq(called, task) {
+                    true => format!("  - {}", problem),
+                    false => format!("  - task '{}': {}", called.name, problem),
+                });
+            }
         }
 
-        for var in task.variables.iter() {
-            debug!("Resolving task variable: {:?}", var);
+        if report.is_empty() {
+            return Ok(());
+        }
+
+        Err(RunnerError(
+            format!(
+                "Task '{}' is missing requirements:\n{}",
+                task.name,
+                report.join("\n")
+            ),
+            RunnerErrorData::MissingRequirements,
+        ))
+    }
+
+    /// Resolves global variables, then the task's own. A variable takes the first value of:
+    ///
+    /// 1. `--var name=value` or `name=value` on the command line
+    /// 2. the `JATR_VAR_<name>` environment variable
+    /// 3. its definition in the task, then in the task file
+    ///
+    /// Overridden command variables do not run their command. Overrides are strings, converted
+    /// to the declared type of the variable
+    ///
+    /// Only variables the task's commands use, directly or through other variables, are
+    /// resolved, plus required ones. Each is resolved once per run, even when several tasks
+    /// use it, and command variables with a `cache` duration reuse output stored on disk
+    fn resolve_variables(&mut self, task: &Task) -> Result<()> {
+        let definitions: Vec<(Option<&str>, &Variable)> = self
+            .task_file
+            .variables
+            .iter()
+            .map(|var| (None, var))
+            .chain(
+                task.variables
+                    .iter()
+                    .map(|var| (Some(task.name.as_str()), var)),
+            )
+            .collect();
+        let (needed, builtins) = self.needed_variables(task, &definitions)?;
+        self.add_builtins(task, &builtins);
+
+        for ((scope, var), needed) in definitions.into_iter().zip(needed) {
+            if !needed {
+                debug!("Skipping unused variable: {}", var.name);
+                continue;
+            }
+
+            let key = (scope.map(String::from), var.name.clone());
+            let memoized = self.memo.borrow().get(&key).cloned();
+            let value = match memoized {
+                Some(value) => value,
+                None => {
+                    match var.secret {
+                        true => debug!("Resolving secret variable: {}", var.name),
+                        false => debug!("Resolving variable: {:?}", var),
+                    }
+
+                    let value = self.resolve_variable(var)?;
+                    self.memo.borrow_mut().insert(key, value.clone());
+                    value
+                }
+            };
 
-            let value = self.resolve_variable(var)?;
             self.templating.add_variable(var.name.as_str(), value);
         }
+
         Ok(())
     }
 
+    /// Marks the definitions the task's commands need. A template sees the definitions before
+    /// its own, so they are walked backwards, each satisfying the names wanted after it. Also
+    /// returns the names no definition satisfies, which are built-ins or undefined
+    fn needed_variables(
+        &self,
+        task: &Task,
+        definitions: &[(Option<&str>, &Variable)],
+    ) -> Result<(Vec<bool>, HashSet<String>)> {
+        let mut wanted = HashSet::new();
+        for action in &task.actions {
+            match action {
+                Action::Command(cmd) | Action::If(cmd) => {
+                    wanted.extend(templating::referenced_variables(&cmd.command)?)
+                }
+                Action::Confirm(message) => {
+                    wanted.extend(templating::referenced_variables(message)?)
+                }
+                Action::Echo(message) => {
+                    wanted.extend(templating::referenced_variables(&message.text)?)
+                }
+                Action::Fail(failure) => {
+                    wanted.extend(templating::referenced_variables(&failure.message)?)
+                }
+                Action::Script(script) => {
+                    wanted.extend(templating::referenced_variables(&script.body)?)
+                }
+                Action::File(action) => {
+                    for template in action.templates() {
+                        wanted.extend(templating::referenced_variables(template)?)
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut needed = vec![false; definitions.len()];
+        for (index, (_, var)) in definitions.iter().enumerate().rev() {
+            if !wanted.remove(&var.name) && !var.constraints.required {
+                continue;
+            }
+
+            needed[index] = true;
+            if self.environment.get_override(&var.name).is_some() {
+                continue;
+            }
+
+            let template = match &var.value {
+                VariableValue::Static(Value::String(s)) => s,
+                VariableValue::Action(Action::Command(cmd)) => &cmd.command,
+                VariableValue::File(path) => path,
+                VariableValue::Prompt(prompt) => &prompt.message,
+                _ => continue,
+            };
+            wanted.extend(templating::referenced_variables(template)?);
+        }
+
+        Ok((needed, wanted))
+    }
+
+    /// Adds the built-in variables to the templates of a task. `git` runs commands, so it is
+    /// only looked up when `used` contains it
+    fn add_builtins(&mut self, task: &Task, used: &HashSet<String>) {
+        let work_dir = self.environment.get_work_dir();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut task_info = IndexMap::new();
+        task_info.insert(String::from("name"), Value::String(task.name.clone()));
+        task_info.insert(
+            String::from("description"),
+            task.description.clone().map_or(Value::Null, Value::String),
+        );
+        task_info.insert(
+            String::from("aliases"),
+            Value::List(task.aliases.iter().cloned().map(Value::String).collect()),
+        );
+
+        let builtins = [
+            ("os", Value::String(String::from(env::consts::OS))),
+            ("arch", Value::String(String::from(env::consts::ARCH))),
+            ("cwd", Value::String(work_dir.clone())),
+            (
+                "task_file_dir",
+                Value::String(self.environment.get_task_file_dir()),
+            ),
+            ("task", Value::Map(task_info)),
+            (
+                "env",
+                Value::Map(env::vars().map(|(k, v)| (k, Value::String(v))).collect()),
+            ),
+            (
+                "args",
+                Value::List(
+                    self.environment
+                        .get_args()
+                        .into_iter()
+                        .map(Value::String)
+                        .collect(),
+                ),
+            ),
+            ("timestamp", Value::Int(timestamp as i64)),
+        ];
+
+        self.templating.work_dir(&work_dir);
+        for (name, value) in builtins {
+            self.templating.add_variable(name, value);
+        }
+
+        if used.contains("git") {
+            // Memoized like a global variable, a task file variable named `git` would have
+            // shadowed the built-in
+            let key = (None, String::from("git"));
+            let memoized = self.memo.borrow().get(&key).cloned();
+            let git = memoized.unwrap_or_else(|| self.git_info());
+            self.memo.borrow_mut().insert(key, git.clone());
+            self.templating.add_variable("git", git);
+        }
+    }
+
+    fn git_info(&self) -> Value {
+        let git = |command: &str| {
+            let request = ExecuteRequest {
+                command: String::from(command),
+                shell: self.environment.get_default_shell(),
+                work_dir: self.environment.get_work_dir(),
+                tty: false,
+            };
+
+            match self.execute(&request) {
+                Ok(result) if result.success() => Value::String(String::from(result.stdout.trim())),
+                _ => Value::Null,
+            }
+        };
+
+        let mut info = IndexMap::new();
+        info.insert(
+            String::from("branch"),
+            git("git rev-parse --abbrev-ref HEAD"),
+        );
+        info.insert(String::from("sha"), git("git rev-parse HEAD"));
+        info.insert(String::from("short_sha"), git("git rev-parse --short HEAD"));
+
+        Value::Map(info)
+    }
+
     fn resolve_variable(&mut self, variable: &Variable) -> Result<Value> {
-        match &variable.value {
-            VariableValue::Static(s) => match s {
+        let overridden = self.environment.get_override(&variable.name);
+        if let (true, Some(value)) = (variable.secret, &overridden) {
+            self.secrets.borrow_mut().add(&Value::String(value.clone()));
+        }
+
+        let value = match (&variable.value, overridden) {
+            // A multiselect answers with a list, given on the command line as `a,b`
+            (VariableValue::Prompt(prompt), Some(value))
+                if prompt.kind == PromptKind::MultiSelect =>
+            {
+                debug!(
+                    "Variable '{}' overridden with '{}'",
+                    variable.name,
+                    self.redact(&value)
+                );
+                Value::List(
+                    value
+                        .split(',')
+                        .filter(|item| !item.is_empty())
+                        .map(|item| Value::String(String::from(item)))
+                        .collect(),
+                )
+            }
+            (_, Some(value)) => {
+                debug!(
+                    "Variable '{}' overridden with '{}'",
+                    variable.name,
+                    self.redact(&value)
+                );
+                Value::String(value)
+            }
+            (VariableValue::Static(s), None) => match s {
                 Value::String(s) => {
                     let templated = self.templating.process(s)?;
-                    Ok(Value::String(templated))
+                    Value::String(templated)
                 }
-                val => Ok(val.clone()),
+                val => val.clone(),
             },
-            VariableValue::Action(action) => {
-                Ok(self.resolve_variable_action(variable.name.as_str(), action)?)
+            (VariableValue::Action(action), None) => {
+                let output = self.resolve_variable_action(variable, action)?;
+
+                // Typed output is trimmed first so `type="int"` reads `42\n` as 42
+                match (output, variable.constraints.value_type) {
+                    (Value::String(s), Some(_)) => Value::String(String::from(s.trim())),
+                    (output, _) => output,
+                }
             }
+            (VariableValue::Prompt(prompt), None) => self.ask(variable, prompt)?,
+            (VariableValue::Env(name), None) => match env::var(name) {
+                Ok(value) => Value::String(value),
+                Err(_) => {
+                    return Err(RunnerError(
+                        format!(
+                            "Error resolving variable '{}'. Environment variable '{}' is not set",
+                            variable.name, name
+                        ),
+                        RunnerErrorData::VariableResolveError {
+                            variable: variable.name.clone(),
+                        },
+                    ))
+                }
+            },
+            (VariableValue::File(path), None) => {
+                let path = self.templating.process(path)?;
+                let path = Utf8Path::new(&self.environment.get_work_dir()).join(path);
+
+                typing::from_file(&path).map_err(|message| {
+                    RunnerError(
+                        format!("Error resolving variable '{}'. {}", variable.name, message),
+                        RunnerErrorData::VariableResolveError {
+                            variable: variable.name.clone(),
+                        },
+                    )
+                })?
+            }
+        };
+
+        // Registered before the checks, whose errors quote the value
+        if variable.secret {
+            self.secrets.borrow_mut().add(&value);
+        }
+
+        let work_dir = self.environment.get_work_dir();
+        let value = typing::check(
+            &variable.name,
+            value,
+            &variable.constraints,
+            Utf8Path::new(&work_dir),
+        )
+        .map_err(|message| {
+            RunnerError(
+                message,
+                RunnerErrorData::InvalidVariable {
+                    variable: variable.name.clone(),
+                },
+            )
+        })?;
+
+        if variable.secret {
+            self.secrets.borrow_mut().add(&value);
         }
+
+        Ok(value)
     }
 
-    fn resolve_variable_action(&mut self, var_name: &str, action: &Action) -> Result<Value> {
+    /// Asks the user for the value of a prompt variable
+    fn ask(&self, variable: &Variable, prompt: &Prompt) -> Result<Value> {
+        let message = self.templating.process(&prompt.message)?;
+
+        if !self.environment.is_interactive() {
+            return Err(RunnerError(
+                format!(
+                    "Variable '{}' asks '{}' but input is not interactive. Set it with \
+                     --var {}=VALUE or {}{}",
+                    variable.name,
+                    message,
+                    variable.name,
+                    environment::OVERRIDE_PREFIX,
+                    variable.name
+                ),
+                RunnerErrorData::InputRequired,
+            ));
+        }
+
+        prompt::ask(prompt, &message, &variable.constraints.choices).map_err(prompt_error)
+    }
+
+    /// Asks a yes/no question, failing when input is not interactive
+    fn confirm(&self, message: &str) -> Result<bool> {
+        if !self.environment.is_interactive() {
+            return Err(RunnerError(
+                format!("Cannot confirm '{}', input is not interactive", message),
+                RunnerErrorData::InputRequired,
+            ));
+        }
+
+        prompt::confirm(message).map_err(prompt_error)
+    }
+
+    fn resolve_variable_action(&mut self, variable: &Variable, action: &Action) -> Result<Value> {
+        let var_name = variable.name.as_str();
+        let cache = variable.cache;
         let Action::Command(cmd) = action else {
             return Err(RunnerError(
                 format!("Invalid action type for variable '{}'", var_name),
@@ -200,18 +769,26 @@ impl<'a> Runner<'a> {
             ));
         };
 
-        let templated_command = self.templating.process(&cmd.command)?;
-        let mut cmd_clone = cmd.clone();
-        cmd_clone.command = templated_command;
+        let request = self.command_request(cmd)?;
+        if let Some(stdout) = cache.and_then(|ttl| cache::load(&request, ttl)) {
+            debug!("Variable '{}' read from cache", var_name);
+            return Ok(Value::String(stdout));
+        }
+
+        // The output of a secret command is masked before it is logged
+        let result = self.executor.execute(&request)?;
+        if variable.secret {
+            self.secrets
+                .borrow_mut()
+                .add(&Value::String(result.stdout.clone()));
+        }
+        self.log_execution(&request, &result);
 
-        let action_result = self.run_action(&Action::Command(cmd_clone), true)?;
-        if !action_result.last_command.exit_status.success() {
+        if !result.success() {
             return Err(RunnerError(
                 format!(
                     "Error resolving variable '{}'. Command '{}' exit code: {}",
-                    var_name,
-                    action_result.last_command.cmd,
-                    action_result.last_command.exit_status.code().unwrap()
+                    var_name, result.cmd, result.exit_code
                 ),
                 RunnerErrorData::VariableResolveError {
                     variable: String::from(var_name),
@@ -219,7 +796,48 @@ impl<'a> Runner<'a> {
             ));
         }
 
-        Ok(Value::String(action_result.last_command.stdout))
+        if cache.is_some() {
+            cache::store(&request, &result.stdout);
+        }
+
+        Ok(Value::String(result.stdout))
+    }
+}
+
+fn prompt_error(e: InquireError) -> RunnerError {
+    match e {
+        InquireError::OperationCanceled | InquireError::OperationInterrupted => RunnerError(
+            String::from("Aborted by the user"),
+            RunnerErrorData::Aborted,
+        ),
+        InquireError::IO(e) => e.into(),
+        e => RunnerError(e.to_string(), RunnerErrorData::InputRequired),
+    }
+}
+
+impl RunnerError {
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+
+    pub fn data(&self) -> &RunnerErrorData {
+        &self.1
+    }
+}
+
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for RunnerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.1 {
+            RunnerErrorData::TemplateError(e) => Some(e),
+            RunnerErrorData::Io(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
@@ -232,7 +850,7 @@ impl From<std::io::Error> for RunnerError {
 impl From<tera::Error> for RunnerError {
     fn from(e: tera::Error) -> Self {
         RunnerError(
-            format!("Template Error: {}", e.to_string()),
+            format!("Template Error: {}", e),
             RunnerErrorData::TemplateError(e),
         )
     }
@@ -1,15 +1,30 @@
+use super::redact::Secrets;
+use crate::tasks::{FileAction, MessageLevel};
 use color_print::{cformat, cstr};
+use std::cell::RefCell;
+use std::rc::Rc;
 
+/// Prints the actions of a task as they run, with secret values masked
 pub struct Output {
     task_name: String,
+    secrets: Rc<RefCell<Secrets>>,
 }
 
 impl Output {
-    pub fn for_task(task_name: &str) -> Self {
+    pub fn for_task(task_name: &str, secrets: Rc<RefCell<Secrets>>) -> Self {
         Self {
             task_name: String::from(task_name),
+            secrets,
         }
     }
+
+    pub fn task_name(&self) -> &str {
+        &self.task_name
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.secrets.borrow().redact(text)
+    }
 }
 
 impl Output {
@@ -17,7 +32,55 @@ impl Output {
         action_println(
             &self.task_name,
             "cmd",
-            cformat!("<green>'{}'</green>", cmd).as_str(),
+            cformat!("<green>'{}'</green>", self.redact(cmd)).as_str(),
+        );
+    }
+
+    pub fn script_execution(&self, interpreter: &str) {
+        action_println(
+            &self.task_name,
+            "script",
+            cformat!("<green>'{}'</green>", self.redact(interpreter)).as_str(),
+        );
+    }
+
+    pub fn file_execution(&self, action: &FileAction) {
+        let content = match action {
+            FileAction::Copy { from, to } | FileAction::Move { from, to } => {
+                cformat!("<green>'{}'</green> -> <green>'{}'</green>", from, to)
+            }
+            FileAction::Symlink { target, link } => {
+                cformat!("<green>'{}'</green> -> <green>'{}'</green>", link, target)
+            }
+            FileAction::Chmod { path, mode } => cformat!("<green>'{}'</green> {:o}", path, mode),
+            action => cformat!("<green>'{}'</green>", action.templates()[0]),
+        };
+
+        action_println(&self.task_name, action.name(), &self.redact(&content));
+    }
+
+    /// Prints a message of an `echo` action. Warnings and errors go to stderr
+    pub fn echo(&self, level: MessageLevel, message: &str) {
+        let message = self.redact(message);
+
+        match level {
+            MessageLevel::Info => action_println(&self.task_name, "echo", &message),
+            MessageLevel::Warn => eprintln!(
+                "{}",
+                action_line(&self.task_name, "warn", &cformat!("<yellow>{}</>", message))
+            ),
+            MessageLevel::Error => eprintln!(
+                "{}",
+                action_line(&self.task_name, "error", &cformat!("<red>{}</>", message))
+            ),
+        }
+    }
+
+    pub fn fail(&self, message: &str) {
+        let message = self.redact(message);
+        eprintln!(
+            "{}",
+            action_line(&self.task_name, "fail", &cformat!("<red>{}</>", message))
         );
     }
 
@@ -25,7 +88,7 @@ impl Output {
         action_println(
             &self.task_name,
             "cd",
-            cformat!("<green>'{}'</green>", cd).as_str(),
+            cformat!("<green>'{}'</green>", self.redact(cd)).as_str(),
         );
     }
 
@@ -40,7 +103,7 @@ impl Output {
             "if",
             cformat!(
                 "<bright-black>'{}'</><white> == '</>{}<bright-black>'</>",
-                cmd,
+                self.redact(cmd),
                 res_str
             )
             .as_str(),
@@ -49,13 +112,14 @@ impl Output {
 }
 
 fn action_println(task: &str, action: &str, content: &str) {
-    println!(
-        "{}",
-        cformat!(
-            "<white>Task '<yellow>{}</>' {}:</> {}",
-            task,
-            action,
-            content
-        )
-    );
+    println!("{}", action_line(task, action, content));
+}
+
+fn action_line(task: &str, action: &str, content: &str) -> String {
+    cformat!(
+        "<white>Task '<yellow>{}</>' {}:</> {}",
+        task,
+        action,
+        content
+    )
 }
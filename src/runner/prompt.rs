@@ -0,0 +1,57 @@
+//! Questions asked on the terminal while a task runs, for `prompt`, `password`, `select` and
+//! `multiselect` variables and `confirm` actions.
+
+use crate::tasks::{Prompt, PromptKind, Value};
+use inquire::{Confirm, InquireError, MultiSelect, Password, Select, Text};
+use std::fmt;
+
+/// Choice offered by a select, shown without the quotes of a string
+struct Choice(Value);
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            value => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Asks the question of a prompt. `choices` are offered by `select` and `multiselect`
+pub fn ask(prompt: &Prompt, message: &str, choices: &[Value]) -> Result<Value, InquireError> {
+    let choices = || choices.iter().cloned().map(Choice).collect();
+
+    let value = match prompt.kind {
+        PromptKind::Text => {
+            let mut text = Text::new(message);
+            if let Some(default) = &prompt.default {
+                text = text.with_default(default);
+            }
+            Value::String(text.prompt()?)
+        }
+        PromptKind::Password => Value::String(
+            Password::new(message)
+                .without_confirmation()
+                .with_display_toggle_enabled()
+                .prompt()?,
+        ),
+        PromptKind::Select => Select::new(message, choices()).prompt()?.0,
+        PromptKind::MultiSelect => Value::List(
+            MultiSelect::new(message, choices())
+                .prompt()?
+                .into_iter()
+                .map(|choice| choice.0)
+                .collect(),
+        ),
+    };
+
+    Ok(value)
+}
+
+/// Asks a yes/no question, no by default
+pub fn confirm(message: &str) -> Result<bool, InquireError> {
+    Confirm::new(message).with_default(false).prompt()
+}
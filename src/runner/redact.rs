@@ -0,0 +1,58 @@
+//! Masks the values of secret variables in the commands, logs and errors jatr prints.
+
+use crate::tasks::Value;
+use std::cmp::Reverse;
+
+pub const MASK: &str = "***";
+
+/// Shortest value that is masked. Shorter values like `1` or `on` turn up all over the output,
+/// masking every occurrence would garble it while hiding little
+pub const MIN_LENGTH: usize = 4;
+
+/// Values of the secret variables resolved so far
+#[derive(Debug, Default)]
+pub struct Secrets {
+    values: Vec<String>,
+}
+
+impl Secrets {
+    /// Adds a secret value. Lists and maps add each of their values. Values shorter than
+    /// [`MIN_LENGTH`] characters are not masked
+    pub fn add(&mut self, value: &Value) {
+        match value {
+            Value::String(s) => {
+                self.values.push(s.clone());
+                self.values.push(String::from(s.trim()));
+            }
+            Value::Int(i) => self.values.push(i.to_string()),
+            Value::Float(f) => self.values.push(f.to_string()),
+            Value::List(items) => items.iter().for_each(|item| self.add(item)),
+            Value::Map(map) => map.values().for_each(|item| self.add(item)),
+            Value::Bool(_) | Value::Null => {}
+        }
+
+        self.values
+            .retain(|value| value.chars().count() >= MIN_LENGTH);
+        self.values.sort();
+        self.values.dedup();
+        // Longer values first, so a secret containing another one is masked whole
+        self.values.sort_by_key(|value| Reverse(value.len()));
+    }
+
+    /// Replaces every secret value in `text` with `***`
+    ///
+    /// ```
+    /// use jatr::runner::redact::Secrets;
+    /// use jatr::tasks::Value;
+    ///
+    /// let mut secrets = Secrets::default();
+    /// secrets.add(&Value::String(String::from("hunter2\n")));
+    ///
+    /// assert_eq!(secrets.redact("login -p hunter2"), "login -p ***");
+    /// ```
+    pub fn redact(&self, text: &str) -> String {
+        self.values
+            .iter()
+            .fold(String::from(text), |text, value| text.replace(value, MASK))
+    }
+}
@@ -0,0 +1,80 @@
+//! Preconditions of tasks, checked before any of their variables or actions.
+
+use super::Result;
+use crate::tasks::{Requirement, RequirementKind};
+use camino::Utf8Path;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use std::env;
+
+/// Checks every requirement and describes the ones not met, each with its hint.
+/// `version_output` runs `<tool> --version` for tools with a version requirement
+pub fn check(
+    requirements: &[Requirement],
+    work_dir: &Utf8Path,
+    mut version_output: impl FnMut(&str) -> Result<String>,
+) -> Result<Vec<String>> {
+    let mut problems = vec![];
+
+    for requirement in requirements {
+        let problem = match &requirement.kind {
+            RequirementKind::Tool { name, version } => match which::which(name) {
+                Err(_) => Some(format!("tool '{}' not found in PATH", name)),
+                Ok(_) => match version {
+                    Some(version) => check_version(name, version, &version_output(name)?),
+                    None => None,
+                },
+            },
+            RequirementKind::Env(name) => match env::var_os(name) {
+                Some(_) => None,
+                None => Some(format!("environment variable '{}' is not set", name)),
+            },
+            RequirementKind::File(path) => match work_dir.join(path).exists() {
+                true => None,
+                false => Some(format!("file '{}' not found", path)),
+            },
+        };
+
+        if let Some(problem) = problem {
+            problems.push(match &requirement.hint {
+                Some(hint) => format!("{}\n    hint: {}", problem, hint),
+                None => problem,
+            });
+        }
+    }
+
+    Ok(problems)
+}
+
+fn check_version(name: &str, requirement: &str, output: &str) -> Option<String> {
+    let Ok(requirement) = VersionReq::parse(requirement) else {
+        return Some(format!("invalid version requirement '{}'", requirement));
+    };
+
+    let Some(version) = parse_version(output) else {
+        return Some(format!(
+            "tool '{}' should be {}, its version could not be read from '{} --version'",
+            name, requirement, name
+        ));
+    };
+
+    match requirement.matches(&version) {
+        true => None,
+        false => Some(format!(
+            "tool '{}' should be {}, found {}",
+            name, requirement, version
+        )),
+    }
+}
+
+/// First version number in the output of `--version`, with missing parts as zero
+fn parse_version(output: &str) -> Option<Version> {
+    let pattern = Regex::new(r"(\d+)\.(\d+)(?:\.(\d+))?").unwrap();
+    let captures = pattern.captures(output)?;
+    let part = |index| match captures.get(index) {
+        Some(part) => part.as_str().parse().ok(),
+        None => Some(0),
+    };
+
+    Some(Version::new(part(1)?, part(2)?, part(3)?))
+}
@@ -0,0 +1,126 @@
+//! Temporary files `script` actions run from.
+
+use crate::tasks::ActionScript;
+use crate::utils::shell_quote;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, io, process};
+
+/// Interpreters run with `-e`, so a script stops at its first failing command
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "ash"];
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Script written to disk, removed when dropped
+#[derive(Debug)]
+pub struct ScriptFile {
+    path: PathBuf,
+    /// Interpreter the file is run with
+    pub interpreter: String,
+    /// Shell command running the file
+    pub command: String,
+}
+
+impl ScriptFile {
+    /// Writes the templated `body` of a script to a temporary file. The interpreter is the
+    /// script's own, else the one of a `#!` first line, else `default_shell`
+    ///
+    /// Lines of the file match the lines of the task file when the script knows where its
+    /// body starts, so interpreters report errors at lines of the task file
+    pub fn create(
+        script: &ActionScript,
+        body: &str,
+        default_shell: &str,
+        task: &str,
+    ) -> io::Result<Self> {
+        let mut lines: Vec<&str> = body.lines().collect();
+        let shebang = lines.first().and_then(|line| line.strip_prefix("#!"));
+        let interpreter = match (&script.interpreter, shebang) {
+            (Some(interpreter), _) => interpreter.clone(),
+            (None, Some(shebang)) => String::from(shebang.trim()),
+            (None, None) => String::from(default_shell),
+        };
+
+        // The shebang is no longer the first line once padded, where interpreters may reject it
+        if shebang.is_some() {
+            lines[0] = "";
+        }
+
+        let padding = "\n".repeat(script.line.unwrap_or(1).saturating_sub(1));
+        let content = format!("{}{}\n", padding, lines.join("\n"));
+
+        let name: String = task
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let (path, mut file) = loop {
+            let path = env::temp_dir().join(format!(
+                "jatr-{}-{}-{}",
+                name,
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+
+            match create_new(&path) {
+                Ok(file) => break (path, file),
+                // Left by an earlier run or planted by someone else, never written through
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let written = file.write_all(content.as_bytes());
+        drop(file);
+        if let Err(e) = written {
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+
+        let command = format!(
+            "{}{} {}",
+            interpreter,
+            if is_shell(&interpreter) { " -e" } else { "" },
+            shell_quote(&path.to_string_lossy())
+        );
+
+        Ok(Self {
+            path,
+            interpreter,
+            command,
+        })
+    }
+}
+
+impl Drop for ScriptFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Creates a file only the user can read, failing when the path exists, also as a symlink
+#[cfg(unix)]
+fn create_new(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_new(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+fn is_shell(interpreter: &str) -> bool {
+    let program = interpreter.split_whitespace().next().unwrap_or_default();
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+
+    SHELLS.contains(&name)
+}
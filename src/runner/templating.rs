@@ -1,13 +1,55 @@
+use super::functions;
 use crate::tasks::Value;
+use camino::Utf8PathBuf;
+use tera::ast::{Expr, ExprVal, FunctionCall, Node};
 
+/// Variables the runner defines for every task, with what they hold. Task file variables of
+/// the same name take precedence
+pub const BUILTINS: &[(&str, &str)] = &[
+    ("os", "Operating system, like `linux` or `macos`"),
+    ("arch", "CPU architecture, like `x86_64` or `aarch64`"),
+    ("cwd", "Working directory of the task, changed by `cd`"),
+    ("task_file_dir", "Directory of the task file"),
+    (
+        "task",
+        "Running task, with `name`, `description` and `aliases`",
+    ),
+    ("env", "Environment variables, like `env.HOME`"),
+    ("args", "Arguments given after `--` on the command line"),
+    (
+        "timestamp",
+        "Seconds since the Unix epoch when the task started",
+    ),
+    (
+        "git",
+        "`branch`, `sha` and `short_sha` of the git checkout, null outside one",
+    ),
+];
+
+/// Tera context holding resolved variables
+///
+/// ```
+/// use jatr::runner::templating::Templating;
+/// use jatr::tasks::Value;
+///
+/// let mut templating = Templating::default();
+/// templating.add_variable("file", Value::String(String::from("my notes.txt")));
+///
+/// assert_eq!(
+///     templating.process("rm {{ file | shell_quote }}").unwrap(),
+///     "rm 'my notes.txt'"
+/// );
+/// ```
 pub struct Templating {
     context: tera::Context,
+    work_dir: Utf8PathBuf,
 }
 
 impl Default for Templating {
     fn default() -> Self {
         Self {
             context: tera::Context::new(),
+            work_dir: Utf8PathBuf::from("."),
         }
     }
 }
@@ -17,7 +59,127 @@ impl Templating {
         self.context.insert(name.to_string(), &value);
     }
 
+    /// Directory relative paths given to template functions are resolved against
+    pub fn work_dir(&mut self, dir: &str) {
+        self.work_dir = Utf8PathBuf::from(dir);
+    }
+
     pub fn process(&self, template: &str) -> Result<String, tera::Error> {
-        tera::Tera::one_off(template, &self.context, false)
+        let mut tera = tera::Tera::default();
+        functions::register(&mut tera, &self.work_dir);
+
+        tera.render_str(template, &self.context)
+    }
+}
+
+/// Returns the names of the context variables a template reads, without loop or `set` locals
+pub fn referenced_variables(template: &str) -> Result<Vec<String>, tera::Error> {
+    let template = tera::Template::new("__tera_one_off", None, template)?;
+    let mut collector = VariableCollector::default();
+    collector.nodes(&template.ast);
+
+    Ok(collector.variables)
+}
+
+#[derive(Default)]
+struct VariableCollector {
+    variables: Vec<String>,
+    locals: Vec<String>,
+}
+
+impl VariableCollector {
+    fn nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::VariableBlock(_, expr) => self.expr(expr),
+            Node::Set(_, set) => {
+                self.expr(&set.value);
+                self.locals.push(set.key.clone());
+            }
+            Node::FilterSection(_, section, _) => {
+                self.function_call(&section.filter);
+                self.nodes(&section.body);
+            }
+            Node::Block(_, block, _) => self.nodes(&block.body),
+            Node::Forloop(_, forloop, _) => {
+                self.expr(&forloop.container);
+
+                let locals = self.locals.len();
+                self.locals.extend(forloop.key.clone());
+                self.locals.push(forloop.value.clone());
+                self.locals.push(String::from("loop"));
+                self.nodes(&forloop.body);
+                self.locals.truncate(locals);
+
+                if let Some(body) = &forloop.empty_body {
+                    self.nodes(body);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, expr, body) in &condition.conditions {
+                    self.expr(expr);
+                    self.nodes(body);
+                }
+
+                if let Some((_, body)) = &condition.otherwise {
+                    self.nodes(body);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        self.expr_val(&expr.val);
+
+        for filter in &expr.filters {
+            self.function_call(filter);
+        }
+    }
+
+    fn expr_val(&mut self, val: &ExprVal) {
+        match val {
+            ExprVal::Ident(ident) => self.ident(ident),
+            ExprVal::Math(math) => {
+                self.expr(&math.lhs);
+                self.expr(&math.rhs);
+            }
+            ExprVal::Logic(logic) => {
+                self.expr(&logic.lhs);
+                self.expr(&logic.rhs);
+            }
+            ExprVal::Test(test) => {
+                self.ident(&test.ident);
+                test.args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprVal::MacroCall(call) => call.args.values().for_each(|arg| self.expr(arg)),
+            ExprVal::FunctionCall(call) => self.function_call(call),
+            ExprVal::Array(items) => items.iter().for_each(|item| self.expr(item)),
+            ExprVal::StringConcat(concat) => concat.values.iter().for_each(|v| self.expr_val(v)),
+            ExprVal::In(expr) => {
+                self.expr(&expr.lhs);
+                self.expr(&expr.rhs);
+            }
+            ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+        }
+    }
+
+    fn function_call(&mut self, call: &FunctionCall) {
+        call.args.values().for_each(|arg| self.expr(arg));
+    }
+
+    fn ident(&mut self, ident: &str) {
+        let root = ident.split(['.', '[']).next().unwrap_or(ident);
+
+        if self.locals.iter().any(|l| l == root) || self.variables.iter().any(|v| v == root) {
+            return;
+        }
+
+        self.variables.push(String::from(root));
     }
 }
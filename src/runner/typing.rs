@@ -0,0 +1,239 @@
+//! Converts resolved variable values to their declared type and checks them against the
+//! constraints of the declaration.
+
+use crate::tasks::{Constraints, Value, VariableType};
+use camino::Utf8Path;
+use indexmap::IndexMap;
+use regex::Regex;
+use std::fs;
+
+/// Converts a value to the declared type of a variable and checks its constraints. Relative
+/// `path` values are resolved against `work_dir`
+///
+/// ```
+/// use camino::Utf8Path;
+/// use jatr::runner::typing;
+/// use jatr::tasks::{Constraints, Value, VariableType};
+///
+/// let constraints = Constraints {
+///     value_type: Some(VariableType::Int),
+///     ..Default::default()
+/// };
+/// let port = typing::check("port", Value::String(String::from("8080")), &constraints, Utf8Path::new("."));
+/// assert_eq!(port, Ok(Value::Int(8080)));
+///
+/// let port = typing::check("port", Value::String(String::from("http")), &constraints, Utf8Path::new("."));
+/// assert_eq!(port.unwrap_err(), "Variable 'port' must be an int, got \"http\"");
+/// ```
+pub fn check(
+    name: &str,
+    value: Value,
+    constraints: &Constraints,
+    work_dir: &Utf8Path,
+) -> Result<Value, String> {
+    let value = match constraints.value_type {
+        Some(value_type) => {
+            coerce(value, value_type, work_dir).map_err(|e| format!("Variable '{}' {}", name, e))?
+        }
+        None => value,
+    };
+
+    if constraints.required && is_empty(&value) {
+        return Err(format!("Variable '{}' is required but has no value", name));
+    }
+
+    if value == Value::Null {
+        return Ok(value);
+    }
+
+    let items = match &value {
+        Value::List(items) => items.iter().collect(),
+        value => vec![value],
+    };
+
+    if !constraints.choices.is_empty() {
+        let choices = constraints
+            .choices
+            .iter()
+            .map(|choice| match constraints.value_type {
+                Some(value_type) => coerce(choice.clone(), value_type, work_dir),
+                None => Ok(choice.clone()),
+            })
+            .collect::<Result<Vec<Value>, String>>()
+            .map_err(|e| format!("Choice of variable '{}' {}", name, e))?;
+
+        if let Some(item) = items.iter().find(|item| !choices.contains(item)) {
+            return Err(format!(
+                "Variable '{}' must be one of: {}, got {}",
+                name,
+                choices
+                    .iter()
+                    .map(display)
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                display(item)
+            ));
+        }
+    }
+
+    if let Some(pattern) = &constraints.pattern {
+        let regex = Regex::new(pattern).map_err(|e| {
+            format!(
+                "Invalid pattern '{}' of variable '{}': {}",
+                pattern, name, e
+            )
+        })?;
+
+        for item in items {
+            let text = match item {
+                Value::String(s) => s.clone(),
+                item => display(item),
+            };
+
+            if !regex.is_match(&text) {
+                return Err(format!(
+                    "Variable '{}' must match '{}', got {}",
+                    name,
+                    pattern,
+                    display(item)
+                ));
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// Converts a value to a type. Strings are parsed, `list` splits them into non-empty lines and
+/// `json` reads them as a JSON document. Null stays null so required checks can report it
+pub fn coerce(
+    value: Value,
+    value_type: VariableType,
+    work_dir: &Utf8Path,
+) -> Result<Value, String> {
+    let converted = match (value_type, &value) {
+        (_, Value::Null) => Some(Value::Null),
+        (VariableType::String, Value::String(_)) => Some(value.clone()),
+        (VariableType::String, Value::Int(_) | Value::Float(_) | Value::Bool(_)) => {
+            Some(Value::String(display(&value)))
+        }
+        (VariableType::Int, Value::Int(_)) => Some(value.clone()),
+        (VariableType::Int, Value::String(s)) => s.trim().parse().ok().map(Value::Int),
+        (VariableType::Bool, Value::Bool(_)) => Some(value.clone()),
+        (VariableType::Bool, Value::String(s)) => match s.trim() {
+            "true" | "yes" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        (VariableType::List, Value::List(_)) => Some(value.clone()),
+        (VariableType::List, Value::String(s)) => Some(Value::List(
+            s.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| Value::String(String::from(line)))
+                .collect(),
+        )),
+        (VariableType::Json, Value::String(s)) => {
+            return serde_json::from_str(s)
+                .map(json_to_value)
+                .map_err(|e| format!("must be valid JSON: {}", e));
+        }
+        (VariableType::Json, _) => Some(value.clone()),
+        (VariableType::Path, Value::String(s)) => {
+            Some(Value::String(work_dir.join(s.trim()).into_string()))
+        }
+        _ => None,
+    };
+
+    converted.ok_or_else(|| {
+        let article = match value_type {
+            VariableType::Int => "an",
+            _ => "a",
+        };
+        format!(
+            "must be {} {}, got {}",
+            article,
+            value_type.name(),
+            display(&value)
+        )
+    })
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::List(items) => items.is_empty(),
+        Value::Map(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Converts parsed JSON into a value, objects becoming maps
+pub fn json_to_value(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => {
+            Value::List(items.into_iter().map(json_to_value).collect())
+        }
+        serde_json::Value::Object(object) => Value::Map(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, json_to_value(value)))
+                .collect::<IndexMap<String, Value>>(),
+        ),
+    }
+}
+
+/// Reads a file into a value. JSON, YAML and TOML files are parsed, picked by their extension,
+/// any other file is read as text without its trailing newline
+pub fn from_file(path: &Utf8Path) -> Result<Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path, e))?;
+
+    let json = match path.extension() {
+        Some("json") => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+        Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+        _ => {
+            let text = content.strip_suffix('\n').unwrap_or(&content);
+            return Ok(Value::String(String::from(
+                text.strip_suffix('\r').unwrap_or(text),
+            )));
+        }
+    };
+
+    json.map(json_to_value)
+        .map_err(|e| format!("Cannot parse '{}': {}", path, e))
+}
+
+/// Value as it is quoted in error messages
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::from("null"),
+        Value::List(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(display)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Value::Map(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(key, value)| format!("{:?}: {}", key, display(value)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
@@ -0,0 +1,481 @@
+//! Machine readable descriptions of the task file dialect, generated from the node
+//! specifications the reader checks task files against.
+//!
+//! * [`metadata`] lists every node with its names, arguments, properties, children and docs
+//! * [`json_schema`] validates YAML, TOML and JSON task files
+//! * [`kdl_schema`] is a [KDL Schema](https://github.com/kdl-org/kdl) document for KDL task files
+
+use crate::reader::node::{NodeSpec, NodeType, ValueType, NODE_TYPES};
+use crate::reader::Scope;
+use crate::writer::{format_node, string_entry, string_property};
+use kdl::{KdlDocument, KdlEntry, KdlNode};
+use serde_json::{json, Map, Value};
+
+/// Action nodes in the order they are documented. The first name of each is the key of the
+/// action in YAML, TOML and JSON task files
+fn action_types() -> impl Iterator<Item = &'static NodeType> {
+    NODE_TYPES.iter().filter(|t| t.allowed_in(&Scope::Actions))
+}
+
+/// Node types that may appear in a scope, each with the names it is recognised by there.
+/// Nodes named by the user have no names
+fn scope_nodes(scope: &Scope) -> Vec<(NodeType, Vec<&'static str>)> {
+    let mut taken: Vec<&str> = vec![];
+    let mut nodes = vec![];
+
+    for node_type in NODE_TYPES.iter().filter(|t| t.allowed_in(scope)) {
+        let names: Vec<&str> = node_type
+            .spec()
+            .names
+            .iter()
+            .filter(|name| !taken.contains(name))
+            .copied()
+            .collect();
+        taken.extend(&names);
+        nodes.push((*node_type, names));
+    }
+
+    nodes
+}
+
+fn node_id(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::Import => "import",
+        NodeType::Default => "default",
+        NodeType::Variables => "variables",
+        NodeType::Variable => "variable",
+        NodeType::ListItem => "list-item",
+        NodeType::MapEntry => "map-entry",
+        NodeType::Enum => "enum",
+        NodeType::Prompt => "prompt",
+        NodeType::Tasks => "tasks",
+        NodeType::Task => "task",
+        NodeType::Alias => "alias",
+        NodeType::Actions => "actions",
+        NodeType::Cmd => "cmd",
+        NodeType::If => "if",
+        NodeType::ActionTask => "task-call",
+        NodeType::ActionCd => "cd",
+        NodeType::Confirm => "confirm",
+        NodeType::Script => "script",
+        NodeType::FilePath => "file-path",
+        NodeType::FileTransfer => "file-transfer",
+        NodeType::Write => "write",
+        NodeType::Chmod => "chmod",
+        NodeType::Echo => "echo",
+        NodeType::Fail => "fail",
+        NodeType::Requires => "requires",
+        NodeType::Requirement => "requirement",
+    }
+}
+
+fn type_name(value_type: ValueType) -> &'static str {
+    match value_type {
+        ValueType::String | ValueType::OneOf(_) => "string",
+        ValueType::Bool => "bool",
+        ValueType::Scalar => "scalar",
+    }
+}
+
+/// Every node of the dialect, and the nodes allowed at the top level of a task file
+///
+/// ```
+/// let metadata = jatr::schema::metadata();
+/// let import = &metadata["nodes"][0];
+///
+/// assert_eq!(import["names"][0], "import");
+/// assert_eq!(import["properties"][1]["values"][0], "npm");
+/// ```
+pub fn metadata() -> Value {
+    let children = |scope: &Scope| -> Vec<Value> {
+        scope_nodes(scope)
+            .into_iter()
+            .map(|(node_type, names)| json!({ "node": node_id(node_type), "names": names }))
+            .collect()
+    };
+
+    let nodes: Vec<Value> = NODE_TYPES
+        .iter()
+        .map(|node_type| {
+            let spec = node_type.spec();
+            let arguments: Vec<Value> = spec
+                .arguments
+                .iter()
+                .map(|argument| {
+                    let mut metadata = json!({
+                        "name": argument.name,
+                        "type": type_name(argument.value_type),
+                        "required": argument.required,
+                        "variadic": argument.variadic,
+                        "doc": argument.doc,
+                    });
+                    if let ValueType::OneOf(values) = argument.value_type {
+                        metadata["values"] = json!(values);
+                    }
+                    metadata
+                })
+                .collect();
+            let properties: Vec<Value> = spec
+                .properties
+                .iter()
+                .map(|property| {
+                    let mut metadata = json!({
+                        "name": property.name,
+                        "type": type_name(property.value_type),
+                        "doc": property.doc,
+                    });
+                    if let ValueType::OneOf(values) = property.value_type {
+                        metadata["values"] = json!(values);
+                    }
+                    metadata
+                })
+                .collect();
+
+            json!({
+                "id": node_id(*node_type),
+                "names": spec.names,
+                "doc": spec.doc,
+                "arguments": arguments,
+                "properties": properties,
+                "children": spec.children.as_ref().map(children),
+            })
+        })
+        .collect();
+
+    json!({
+        "document": children(&Scope::Global),
+        "nodes": nodes,
+    })
+}
+
+fn value_schema(value_type: ValueType, doc: &str) -> Value {
+    let mut schema = match value_type {
+        ValueType::String => json!({ "type": "string" }),
+        ValueType::Bool => json!({ "type": "boolean" }),
+        ValueType::Scalar => json!({ "type": ["string", "number", "boolean", "null"] }),
+        ValueType::OneOf(values) => json!({ "enum": values }),
+    };
+    schema["description"] = json!(doc);
+
+    schema
+}
+
+fn argument_schema(spec: &NodeSpec) -> Value {
+    let argument = &spec.arguments[0];
+    value_schema(argument.value_type, argument.doc)
+}
+
+/// Object with the node's properties, `key` set to its first argument and its other arguments
+/// under their names
+fn object_schema(spec: &NodeSpec, key: &str) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![key];
+    properties.insert(String::from(key), argument_schema(spec));
+    for argument in spec.arguments.iter().skip(1) {
+        properties.insert(
+            String::from(argument.name),
+            value_schema(argument.value_type, argument.doc),
+        );
+        if argument.required {
+            required.push(argument.name);
+        }
+    }
+    for property in spec.properties {
+        properties.insert(
+            String::from(property.name),
+            value_schema(property.value_type, property.doc),
+        );
+    }
+
+    json!({
+        "type": "object",
+        "description": spec.doc,
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Variable written as a map, with its value or command and the checks it must pass
+fn declared_variable_schema() -> Value {
+    let variable = NodeType::Variable.spec();
+    let cmd = NodeType::Cmd.spec();
+    let choices = NodeType::Enum.spec();
+    let prompt = NodeType::Prompt.spec();
+
+    let mut properties = Map::new();
+    // Lists and maps are only written under `value`, a bare map is read as the declaration
+    let mut value = argument_schema(variable);
+    value["type"] = json!(["string", "number", "boolean", "null", "array", "object"]);
+    properties.insert(String::from("value"), value);
+    properties.insert(String::from("cmd"), argument_schema(cmd));
+    for name in prompt.names {
+        properties.insert(String::from(*name), argument_schema(prompt));
+    }
+    for property in cmd
+        .properties
+        .iter()
+        .chain(variable.properties)
+        .chain(prompt.properties)
+    {
+        properties.insert(
+            String::from(property.name),
+            value_schema(property.value_type, property.doc),
+        );
+    }
+    properties.insert(
+        String::from("enum"),
+        json!({
+            "type": "array",
+            "description": choices.doc,
+            "items": argument_schema(choices),
+        }),
+    );
+
+    json!({
+        "type": "object",
+        "description": variable.doc,
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+/// JSON Schema (draft 2020-12) for task files written in YAML, TOML or JSON
+pub fn json_schema() -> Value {
+    let import = NodeType::Import.spec();
+    let variable = NodeType::Variable.spec();
+    let task = NodeType::Task.spec();
+    let cmd = NodeType::Cmd.spec();
+
+    let mut actions = vec![argument_schema(cmd)];
+    for action_type in action_types() {
+        let spec = action_type.spec();
+        // `-` is only the KDL shorthand of `cmd`
+        for name in spec.names.iter().filter(|name| **name != "-") {
+            actions.push(object_schema(spec, name));
+        }
+    }
+
+    let requirement = NodeType::Requirement.spec();
+    let requirements: Vec<Value> = requirement
+        .names
+        .iter()
+        .map(|name| object_schema(requirement, name))
+        .collect();
+
+    let private = task.property("private").unwrap();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "jatr task file",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "default": argument_schema(NodeType::Default.spec()),
+            "imports": {
+                "type": "array",
+                "description": import.doc,
+                "items": {
+                    "anyOf": [argument_schema(import), object_schema(import, "path")],
+                },
+            },
+            "variables": { "$ref": "#/$defs/variables" },
+            "tasks": {
+                "type": "object",
+                "description": NodeType::Tasks.spec().doc,
+                "additionalProperties": { "$ref": "#/$defs/task" },
+            },
+        },
+        "$defs": {
+            "variables": {
+                "type": "object",
+                "description": NodeType::Variables.spec().doc,
+                "additionalProperties": { "$ref": "#/$defs/variable" },
+            },
+            "variable": {
+                "description": variable.doc,
+                "anyOf": [
+                    argument_schema(variable),
+                    {
+                        "type": "array",
+                        "description": NodeType::ListItem.spec().doc,
+                        "items": argument_schema(NodeType::ListItem.spec()),
+                    },
+                    declared_variable_schema(),
+                ],
+            },
+            "task": {
+                "type": "object",
+                "description": task.doc,
+                "additionalProperties": false,
+                "properties": {
+                    "description": argument_schema(task),
+                    "private": value_schema(private.value_type, private.doc),
+                    "aliases": {
+                        "type": "array",
+                        "description": NodeType::Alias.spec().doc,
+                        "items": argument_schema(NodeType::Alias.spec()),
+                    },
+                    "variables": { "$ref": "#/$defs/variables" },
+                    "requires": {
+                        "type": "array",
+                        "description": NodeType::Requires.spec().doc,
+                        "items": { "anyOf": requirements },
+                    },
+                    "actions": {
+                        "type": "array",
+                        "description": NodeType::Actions.spec().doc,
+                        "items": { "$ref": "#/$defs/action" },
+                    },
+                },
+            },
+            "action": { "anyOf": actions },
+        },
+    })
+}
+
+fn kdl_type_entries(value_type: ValueType) -> Vec<KdlEntry> {
+    match value_type {
+        ValueType::String | ValueType::OneOf(_) => vec![string_entry("string")],
+        ValueType::Bool => vec![string_entry("boolean")],
+        ValueType::Scalar => ["string", "number", "boolean", "null"]
+            .into_iter()
+            .map(string_entry)
+            .collect(),
+    }
+}
+
+/// `type` and `enum` validations of a value or property
+fn kdl_validations(value_type: ValueType) -> Vec<KdlNode> {
+    let mut type_node = KdlNode::new("type");
+    type_node.entries_mut().extend(kdl_type_entries(value_type));
+
+    let mut nodes = vec![type_node];
+
+    if let ValueType::OneOf(values) = value_type {
+        let mut enum_node = KdlNode::new("enum");
+        enum_node
+            .entries_mut()
+            .extend(values.iter().map(|v| string_entry(v)));
+        nodes.push(enum_node);
+    }
+
+    nodes
+}
+
+fn kdl_count(name: &str, count: usize) -> KdlNode {
+    let mut node = KdlNode::new(name);
+    node.push(KdlEntry::new(count as i128));
+    node
+}
+
+/// Scopes whose nodes may contain the same scope again, like the entries of a map. The map
+/// scope is described once under `definitions` and referred to by id
+fn is_recursive(scope: &Scope) -> bool {
+    scope_nodes(scope)
+        .iter()
+        .any(|(node_type, _)| node_type.spec().children.as_ref() == Some(scope))
+}
+
+fn kdl_scope_id(scope: &Scope) -> String {
+    format!("{:?}", scope).to_lowercase()
+}
+
+/// Schema nodes for everything allowed in a scope, one per name a node is recognised by
+fn kdl_nodes(scope: &Scope) -> Vec<KdlNode> {
+    let mut nodes = vec![];
+
+    for (node_type, names) in scope_nodes(scope) {
+        let spec = node_type.spec();
+        let names: Vec<Option<&str>> = match spec.names.is_empty() {
+            true => vec![None],
+            false => names.into_iter().map(Some).collect(),
+        };
+
+        for name in names {
+            let mut node = KdlNode::new("node");
+            if let Some(name) = name {
+                node.push(string_entry(name));
+            }
+            node.push(string_property("description", spec.doc));
+
+            let children = node.ensure_children();
+            for argument in spec.arguments {
+                let mut value = KdlNode::new("value");
+                value.push(string_property("description", argument.doc));
+                let validations = value.ensure_children();
+                validations
+                    .nodes_mut()
+                    .extend(kdl_validations(argument.value_type));
+                validations
+                    .nodes_mut()
+                    .push(kdl_count("min", argument.required as usize));
+                if !argument.variadic {
+                    validations.nodes_mut().push(kdl_count("max", 1));
+                }
+                children.nodes_mut().push(value);
+            }
+
+            for property in spec.properties {
+                let mut prop = KdlNode::new("prop");
+                prop.push(string_entry(property.name));
+                prop.push(string_property("description", property.doc));
+                prop.ensure_children()
+                    .nodes_mut()
+                    .extend(kdl_validations(property.value_type));
+                children.nodes_mut().push(prop);
+            }
+
+            if let Some(child_scope) = &spec.children {
+                let mut child_nodes = KdlNode::new("children");
+                match is_recursive(child_scope) {
+                    true => child_nodes.push(string_property(
+                        "ref",
+                        &format!("[id=\"{}\"]", kdl_scope_id(child_scope)),
+                    )),
+                    false => child_nodes
+                        .ensure_children()
+                        .nodes_mut()
+                        .extend(kdl_nodes(child_scope)),
+                }
+                children.nodes_mut().push(child_nodes);
+            }
+
+            if children.nodes().is_empty() {
+                node.clear_children();
+            }
+            nodes.push(node);
+        }
+    }
+
+    nodes
+}
+
+/// KDL Schema document for task files written in KDL
+pub fn kdl_schema() -> KdlDocument {
+    let mut info = KdlNode::new("info");
+    let mut title = KdlNode::new("title");
+    title.push(string_entry("jatr task file"));
+    info.ensure_children().nodes_mut().push(title);
+
+    let mut document = KdlNode::new("document");
+    let children = document.ensure_children();
+    children.nodes_mut().push(info);
+    children.nodes_mut().extend(kdl_nodes(&Scope::Global));
+
+    let mut map = KdlNode::new("children");
+    map.push(string_property("id", &kdl_scope_id(&Scope::Map)));
+    map.ensure_children()
+        .nodes_mut()
+        .extend(kdl_nodes(&Scope::Map));
+    let mut definitions = KdlNode::new("definitions");
+    definitions.ensure_children().nodes_mut().push(map);
+    children.nodes_mut().push(definitions);
+
+    format_node(&mut document, 0);
+
+    let mut schema = KdlDocument::new();
+    schema.nodes_mut().push(document);
+
+    schema
+}
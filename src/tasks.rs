@@ -1,20 +1,125 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use camino::Utf8PathBuf;
+use indexmap::IndexMap;
+use std::time::Duration;
 
+/// Parsed task file with its imports merged in
 #[derive(Debug, Default)]
 pub struct TaskFile {
+    pub imports: Vec<Import>,
     pub variables: Vec<Variable>,
-    pub tasks: HashMap<String, Task>,
+    pub tasks: IndexMap<String, Task>,
+    pub default: Option<String>,
 }
 
+/// `import` node as written in the task file
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub prefix: Option<String>,
+    /// Build tool the imported file belongs to, `None` for task files
+    pub format: Option<ImportFormat>,
+}
+
+/// Build definitions of other tools that can be imported as tasks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Npm,
+    Yarn,
+    Pnpm,
+    Make,
+    Just,
+}
+
+impl ImportFormat {
+    pub const NAMES: &'static [&'static str] = &["npm", "yarn", "pnpm", "make", "just"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "npm" => Some(ImportFormat::Npm),
+            "yarn" => Some(ImportFormat::Yarn),
+            "pnpm" => Some(ImportFormat::Pnpm),
+            "make" => Some(ImportFormat::Make),
+            "just" => Some(ImportFormat::Just),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportFormat::Npm => "npm",
+            ImportFormat::Yarn => "yarn",
+            ImportFormat::Pnpm => "pnpm",
+            ImportFormat::Make => "make",
+            ImportFormat::Just => "just",
+        }
+    }
+}
+
+impl TaskFile {
+    /// Looks a task up by name or alias
+    pub fn find_task(&self, name: &str) -> Option<&Task> {
+        if let Some(task) = self.tasks.get(name) {
+            return Some(task);
+        }
+
+        self.tasks
+            .values()
+            .find(|task| task.aliases.iter().any(|alias| alias == name))
+    }
+
+    /// First of `key` and the aliases of `task` that already names a task in the file, or that
+    /// repeats an earlier name of the task itself
+    pub fn duplicate_name<'a>(&self, key: &'a str, task: &'a Task) -> Option<&'a str> {
+        let names: Vec<&str> = std::iter::once(key)
+            .chain(task.aliases.iter().map(String::as_str))
+            .collect();
+
+        names
+            .iter()
+            .enumerate()
+            .find(|(index, name)| self.find_task(name).is_some() || names[..*index].contains(name))
+            .map(|(_, name)| *name)
+    }
+}
+
+/// A named list of actions with its own variables
 #[derive(Debug, Clone, Default)]
 pub struct Task {
     pub name: String,
     pub description: Option<String>,
+    pub aliases: Vec<String>,
+    pub private: bool,
     pub actions: Vec<Action>,
     pub variables: Vec<Variable>,
+    /// Checked before the run starts, also when the task is called by another one
+    pub requirements: Vec<Requirement>,
+    /// File the task was imported from, `None` when defined in the file itself
+    pub source: Option<Utf8PathBuf>,
+}
+
+/// Precondition of a task, with a hint on how to meet it
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub kind: RequirementKind,
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RequirementKind {
+    /// Program in PATH. `version` is a semver requirement the output of `<name> --version` must
+    /// match
+    Tool {
+        name: String,
+        version: Option<String>,
+    },
+    /// Environment variable that must be set
+    Env(String),
+    /// File or directory, relative to the working directory
+    File(String),
 }
 
+/// A single step of a task
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Action {
     Noop,
@@ -22,6 +127,13 @@ pub enum Action {
     If(ActionCommand),
     Task(TaskCall),
     Cd(String),
+    /// Asks the user a yes/no question and aborts the run unless answered yes
+    Confirm(String),
+    Script(ActionScript),
+    File(FileAction),
+    Echo(Message),
+    /// Stops the run with a message and exit code
+    Fail(Failure),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,6 +141,7 @@ pub struct Argument {
     pub name: String,
 }
 
+/// Shell command used by `cmd` and `if` actions and command-backed variables
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ActionCommand {
     pub command: String,
@@ -36,23 +149,189 @@ pub struct ActionCommand {
     pub tty: bool,
 }
 
+/// Multi-line script written to a temporary file and run with an interpreter
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ActionScript {
+    pub body: String,
+    /// Command the file is passed to. `None` uses the shebang of the body, else the shell
+    pub interpreter: Option<String>,
+    /// Line of the task file the body starts at, so errors point into the task file
+    pub line: Option<usize>,
+}
+
+/// Filesystem change made without a shell. Paths are templates, relative to the working directory
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum FileAction {
+    /// Creates a directory and its parents
+    Mkdir(String),
+    /// Removes a file or a directory tree, if it exists
+    Remove(String),
+    /// Creates an empty file or updates its modification time
+    Touch(String),
+    Copy {
+        from: String,
+        to: String,
+    },
+    Move {
+        from: String,
+        to: String,
+    },
+    /// Creates `link` pointing to `target`. The target is kept as written, relative to the link
+    Symlink {
+        target: String,
+        link: String,
+    },
+    /// Writes templated content to a file, creating its directory
+    Write {
+        path: String,
+        content: String,
+    },
+    Chmod {
+        path: String,
+        mode: u32,
+    },
+}
+
+impl FileAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileAction::Mkdir(_) => "mkdir",
+            FileAction::Remove(_) => "rm",
+            FileAction::Touch(_) => "touch",
+            FileAction::Copy { .. } => "copy",
+            FileAction::Move { .. } => "move",
+            FileAction::Symlink { .. } => "symlink",
+            FileAction::Write { .. } => "write",
+            FileAction::Chmod { .. } => "chmod",
+        }
+    }
+
+    /// Templates of the action, paths first
+    pub fn templates(&self) -> Vec<&str> {
+        match self {
+            FileAction::Mkdir(path) | FileAction::Remove(path) | FileAction::Touch(path) => {
+                vec![path]
+            }
+            FileAction::Copy { from, to } | FileAction::Move { from, to } => vec![from, to],
+            FileAction::Symlink { target, link } => vec![target, link],
+            FileAction::Write { path, content } => vec![path, content],
+            FileAction::Chmod { path, .. } => vec![path],
+        }
+    }
+}
+
+/// Message printed by an `echo` action
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Message {
+    pub text: String,
+    pub level: MessageLevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum MessageLevel {
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl MessageLevel {
+    pub const NAMES: &'static [&'static str] = &["info", "warn", "error"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(MessageLevel::Info),
+            "warn" => Some(MessageLevel::Warn),
+            "error" => Some(MessageLevel::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageLevel::Info => "info",
+            MessageLevel::Warn => "warn",
+            MessageLevel::Error => "error",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Failure {
+    pub message: String,
+    /// Exit code of jatr, 1 by default
+    pub code: i32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TaskCall {
     pub name: String,
 }
 
+/// Where a variable gets its value from
 #[derive(Debug, Clone)]
 pub enum VariableValue {
     Static(Value),
     Action(Action),
+    /// File read into a value, its path rendered as a template. JSON, YAML and TOML files are
+    /// parsed, others read as text
+    File(String),
+    /// Environment variable of the jatr process
+    Env(String),
+    /// Asked from the user when the task runs
+    Prompt(Prompt),
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Question asked for a variable value. `select` and `multiselect` offer the variable's choices
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub message: String,
+    /// Answer of a `prompt` left empty
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptKind {
+    Text,
+    Password,
+    Select,
+    MultiSelect,
+}
+
+impl PromptKind {
+    pub const NAMES: &'static [&'static str] = &["prompt", "password", "select", "multiselect"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "prompt" => Some(PromptKind::Text),
+            "password" => Some(PromptKind::Password),
+            "select" => Some(PromptKind::Select),
+            "multiselect" => Some(PromptKind::MultiSelect),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PromptKind::Text => "prompt",
+            PromptKind::Password => "password",
+            PromptKind::Select => "select",
+            PromptKind::MultiSelect => "multiselect",
+        }
+    }
+}
+
+/// Resolved variable value available to templates. Serialized untagged so templates see plain
+/// numbers, lists and maps
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
 pub enum Value {
     String(String),
     Int(i64),
     Float(f64),
     List(Vec<Value>),
+    Map(IndexMap<String, Value>),
     Bool(bool),
     Null
 }
@@ -61,4 +340,62 @@ pub enum Value {
 pub struct Variable {
     pub name: String,
     pub value: VariableValue,
+    pub constraints: Constraints,
+    /// How long the output of a command variable is kept in the on-disk cache
+    pub cache: Option<Duration>,
+    /// The value is masked as `***` wherever jatr prints it
+    pub secret: bool,
+    /// File the variable was imported from, `None` when defined in the file itself
+    pub source: Option<Utf8PathBuf>,
+}
+
+/// Declared type and checks a variable's value must pass before any action runs
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    pub value_type: Option<VariableType>,
+    /// The value may not be null or empty
+    pub required: bool,
+    /// Values the variable may take, empty when any value is allowed
+    pub choices: Vec<Value>,
+    /// Regular expression string values must match
+    pub pattern: Option<String>,
+}
+
+/// Type a variable's value is converted to. Command output is trimmed, split into lines or
+/// parsed as JSON accordingly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableType {
+    String,
+    Int,
+    Bool,
+    List,
+    Json,
+    Path,
+}
+
+impl VariableType {
+    pub const NAMES: &'static [&'static str] = &["string", "int", "bool", "list", "json", "path"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "string" => Some(VariableType::String),
+            "int" => Some(VariableType::Int),
+            "bool" => Some(VariableType::Bool),
+            "list" => Some(VariableType::List),
+            "json" => Some(VariableType::Json),
+            "path" => Some(VariableType::Path),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariableType::String => "string",
+            VariableType::Int => "int",
+            VariableType::Bool => "bool",
+            VariableType::List => "list",
+            VariableType::Json => "json",
+            VariableType::Path => "path",
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use crate::tasks::Value;
 use kdl::KdlValue;
+use std::time::Duration;
 
+#[doc(hidden)]
 #[macro_export]
 macro_rules! S {
     ($l:expr) => {
@@ -12,11 +14,90 @@ pub fn kdl_value_to_value(value: &KdlValue) -> Value {
     match value {
         KdlValue::String(s) => Value::String(s.clone()),
         KdlValue::Integer(i) => {
-            let val: i64 = i.clone().try_into().unwrap();
+            let val: i64 = (*i).try_into().unwrap();
             Value::Int(val)
         }
-        KdlValue::Float(f) => Value::Float(f.clone()),
+        KdlValue::Float(f) => Value::Float(*f),
         KdlValue::Bool(b) => Value::Bool(*b),
         KdlValue::Null => Value::Null,
     }
 }
+
+/// Converts a byte offset into a 1-based line and column
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+    (line, column)
+}
+
+/// Picks the candidate closest to `name`, if any is close enough to be a likely typo
+pub fn did_you_mean<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), *candidate))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Quotes a word for POSIX shells, leaving it as is when no quoting is needed
+pub fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+
+    match !word.is_empty() && word.chars().all(safe) {
+        true => String::from(word),
+        false => format!("'{}'", word.replace('\'', r"'\''")),
+    }
+}
+
+/// Parses octal permission bits like `755`, `0644` or `0o600`
+pub fn parse_mode(text: &str) -> Option<u32> {
+    let digits = text.strip_prefix("0o").unwrap_or(text);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+}
+
+/// Parses a duration like `90s`, `15m`, `1h30m` or `7d`
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let mut seconds = 0;
+    let mut number = String::new();
+
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        seconds += value * unit;
+        number.clear();
+    }
+
+    match number.is_empty() && !text.trim().is_empty() {
+        true => Some(Duration::from_secs(seconds)),
+        false => None,
+    }
+}
+
+/// Writes a duration in the largest unit that represents it exactly
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    match seconds {
+        0 => String::from("0s"),
+        s if s % (24 * 60 * 60) == 0 => format!("{}d", s / (24 * 60 * 60)),
+        s if s % (60 * 60) == 0 => format!("{}h", s / (60 * 60)),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
@@ -0,0 +1,334 @@
+use crate::runner::{templating, typing};
+use crate::tasks::{
+    Action, ActionCommand, ActionScript, Constraints, Requirement, RequirementKind, Task, TaskFile,
+    Value, Variable, VariableValue,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use regex::Regex;
+use serde::Serialize;
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(location: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            location: String::from(location),
+            message,
+        }
+    }
+
+    pub fn warning(location: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            location: String::from(location),
+            message,
+        }
+    }
+}
+
+struct Validator<'a> {
+    task_file: &'a TaskFile,
+    base_dir: &'a Utf8Path,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Statically checks a parsed task file. Relative `cd` targets are resolved against `base_dir`
+pub fn validate(task_file: &TaskFile, base_dir: &Utf8Path) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        task_file,
+        base_dir,
+        diagnostics: vec![],
+    };
+
+    validator.validate();
+    validator.diagnostics
+}
+
+impl Validator<'_> {
+    fn validate(&mut self) {
+        if let Some(default) = &self.task_file.default {
+            if self.task_file.find_task(default).is_none() {
+                self.diagnostics.push(Diagnostic::error(
+                    "default",
+                    format!("Default task '{}' not found", default),
+                ));
+            }
+        }
+
+        self.validate_names();
+
+        let mut defined: Vec<String> = templating::BUILTINS
+            .iter()
+            .map(|(name, _)| String::from(*name))
+            .collect();
+        for variable in &self.task_file.variables {
+            let location = format!("variable '{}'", variable.name);
+            self.validate_variable(variable, &location, &defined);
+            defined.push(variable.name.clone());
+        }
+
+        let mut tasks: Vec<&Task> = self.task_file.tasks.values().collect();
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for task in tasks {
+            self.validate_task(task, defined.clone());
+        }
+    }
+
+    /// Task names and aliases become subcommands, which have to be unique
+    fn validate_names(&mut self) {
+        let mut seen: Vec<(&str, &str)> = vec![];
+
+        for (key, task) in &self.task_file.tasks {
+            for name in std::iter::once(key).chain(&task.aliases) {
+                match seen.iter().find(|(seen_name, _)| *seen_name == name) {
+                    Some((_, owner)) => self.diagnostics.push(Diagnostic::error(
+                        &format!("task '{}'", task.name),
+                        format!(
+                            "Name or alias '{}' is already used by task '{}'",
+                            name, owner
+                        ),
+                    )),
+                    None => seen.push((name, key)),
+                }
+            }
+        }
+    }
+
+    fn validate_task(&mut self, task: &Task, mut defined: Vec<String>) {
+        let location = format!("task '{}'", task.name);
+
+        for requirement in &task.requirements {
+            self.validate_requirement(requirement, &location);
+        }
+
+        for variable in &task.variables {
+            self.validate_variable(variable, &location, &defined);
+            defined.push(variable.name.clone());
+        }
+
+        let mut work_dir = self.base_dir.to_path_buf();
+        for action in &task.actions {
+            self.validate_action(action, &location, &defined, &mut work_dir);
+        }
+    }
+
+    fn validate_requirement(&mut self, requirement: &Requirement, location: &str) {
+        match &requirement.kind {
+            RequirementKind::Tool {
+                name,
+                version: Some(version),
+            } => {
+                if let Err(e) = semver::VersionReq::parse(version) {
+                    self.diagnostics.push(Diagnostic::error(
+                        location,
+                        format!(
+                            "Invalid version requirement '{}' of tool '{}': {}",
+                            version,
+                            name,
+                            root_cause(&e)
+                        ),
+                    ));
+                }
+            }
+            RequirementKind::Env(name) if name.is_empty() || name.contains(['=', '\0']) => {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!("Invalid environment variable name '{}'", name),
+                ))
+            }
+            RequirementKind::Tool { .. } | RequirementKind::Env(_) | RequirementKind::File(_) => {}
+        }
+    }
+
+    fn validate_variable(&mut self, variable: &Variable, location: &str, defined: &[String]) {
+        match &variable.value {
+            VariableValue::Static(Value::String(s)) => self.validate_template(s, location, defined),
+            VariableValue::Static(_) => {}
+            VariableValue::Action(Action::Command(cmd)) => {
+                self.validate_command(cmd, location, defined)
+            }
+            VariableValue::Action(_) => self.diagnostics.push(Diagnostic::error(
+                location,
+                format!("Invalid action type for variable '{}'", variable.name),
+            )),
+            VariableValue::File(path) if path.contains("{{") || path.contains("{%") => {
+                self.validate_template(path, location, defined)
+            }
+            VariableValue::Prompt(prompt) => {
+                self.validate_template(&prompt.message, location, defined)
+            }
+            VariableValue::Env(_) => {}
+            VariableValue::File(path) => {
+                let file = self.base_dir.join(path);
+                if !file.is_file() {
+                    self.diagnostics.push(Diagnostic::warning(
+                        location,
+                        format!(
+                            "File '{}' of variable '{}' does not exist",
+                            file, variable.name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        self.validate_constraints(variable, location);
+    }
+
+    /// Checks static values against the declared type and constraints. Values rendered from
+    /// templates or commands are only known when the task runs
+    fn validate_constraints(&mut self, variable: &Variable, location: &str) {
+        if let Some(pattern) = &variable.constraints.pattern {
+            if let Err(e) = Regex::new(pattern) {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!(
+                        "Invalid pattern '{}' of variable '{}': {}",
+                        pattern,
+                        variable.name,
+                        root_cause(&e)
+                    ),
+                ));
+                return;
+            }
+        }
+
+        let (value, constraints) = match &variable.value {
+            VariableValue::Static(Value::String(s)) if s.contains("{{") || s.contains("{%") => {
+                return
+            }
+            VariableValue::Static(value) if *value != Value::Null => {
+                (value.clone(), variable.constraints.clone())
+            }
+            // Only the choices can be checked before the command runs. Required variables
+            // without a value get one from the command line or environment
+            _ => (
+                Value::Null,
+                Constraints {
+                    required: false,
+                    ..variable.constraints.clone()
+                },
+            ),
+        };
+
+        if let Err(message) = typing::check(&variable.name, value, &constraints, self.base_dir) {
+            self.diagnostics.push(Diagnostic::error(location, message));
+        }
+    }
+
+    fn validate_action(
+        &mut self,
+        action: &Action,
+        location: &str,
+        defined: &[String],
+        work_dir: &mut Utf8PathBuf,
+    ) {
+        match action {
+            Action::Command(cmd) | Action::If(cmd) => self.validate_command(cmd, location, defined),
+            Action::Task(call) => {
+                if self.task_file.find_task(&call.name).is_none() {
+                    self.diagnostics.push(Diagnostic::error(
+                        location,
+                        format!("Task '{}' not found", call.name),
+                    ));
+                }
+            }
+            Action::Cd(path) => {
+                let target = work_dir.join(path);
+
+                match target.is_dir() {
+                    true => *work_dir = target,
+                    false => self.diagnostics.push(Diagnostic::warning(
+                        location,
+                        format!("cd target '{}' does not exist", target),
+                    )),
+                }
+            }
+            Action::Confirm(message) => self.validate_template(message, location, defined),
+            Action::Echo(message) => self.validate_template(&message.text, location, defined),
+            Action::Fail(failure) => self.validate_template(&failure.message, location, defined),
+            Action::Script(script) => self.validate_script(script, location, defined),
+            Action::File(action) => {
+                for template in action.templates() {
+                    self.validate_template(template, location, defined);
+                }
+            }
+            Action::Noop => {}
+        }
+    }
+
+    fn validate_command(&mut self, cmd: &ActionCommand, location: &str, defined: &[String]) {
+        self.validate_template(&cmd.command, location, defined);
+
+        if let Some(shell) = &cmd.shell {
+            if which::which(shell).is_err() {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!("Shell '{}' not found in PATH", shell),
+                ));
+            }
+        }
+    }
+
+    fn validate_script(&mut self, script: &ActionScript, location: &str, defined: &[String]) {
+        self.validate_template(&script.body, location, defined);
+
+        let program = script
+            .interpreter
+            .as_deref()
+            .and_then(|interpreter| interpreter.split_whitespace().next());
+
+        if let Some(program) = program {
+            if which::which(program).is_err() {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!("Interpreter '{}' not found in PATH", program),
+                ));
+            }
+        }
+    }
+
+    fn validate_template(&mut self, template: &str, location: &str, defined: &[String]) {
+        let variables = match templating::referenced_variables(template) {
+            Ok(variables) => variables,
+            Err(e) => {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!("Invalid template '{}': {}", template, root_cause(&e)),
+                ));
+                return;
+            }
+        };
+
+        for variable in variables {
+            if !defined.contains(&variable) {
+                self.diagnostics.push(Diagnostic::error(
+                    location,
+                    format!("Undefined variable '{}' in '{}'", variable, template),
+                ));
+            }
+        }
+    }
+}
+
+fn root_cause(e: &dyn Error) -> String {
+    match e.source() {
+        Some(source) => root_cause(source),
+        None => e.to_string(),
+    }
+}
@@ -0,0 +1,154 @@
+use super::{is_action, quote, Block};
+use kdl::{KdlDocument, KdlNode, KdlValue};
+
+/// Canonically formats a task file document in place. Names are spelled the canonical way,
+/// sections are ordered, strings are quoted and indentation is normalised. Comments and
+/// single blank lines between nodes are kept
+///
+/// ```
+/// let mut document = "tasks {\n  build { - build; vars { mode debug; }\n  }\n}\ndefault build"
+///     .parse()
+///     .unwrap();
+///
+/// jatr::writer::format(&mut document);
+///
+/// assert_eq!(
+///     document.to_string(),
+///     "default \"build\"\n\ntasks {\n    build {\n        variables {\n            mode \"debug\"\n        }\n\n        cmd \"build\"\n    }\n}\n"
+/// );
+/// ```
+pub fn format(document: &mut KdlDocument) {
+    let mut blank_lines = vec![];
+    prepare(document, Block::Document, &mut blank_lines);
+
+    document.autoformat();
+
+    // Comments after the last node end up in the document's trailing decoration
+    if let Some(format) = document.format_mut() {
+        format.trailing = match format.trailing.trim() {
+            "" => String::new(),
+            trailing => format!("\n{}\n", trailing),
+        };
+    }
+
+    let mut blank_lines = blank_lines.into_iter();
+    space(document, Block::Document, &mut blank_lines);
+}
+
+/// Renames, requotes and orders nodes, recording in traversal order which nodes were
+/// preceded by a blank line since autoformat removes them
+fn prepare(document: &mut KdlDocument, block: Block, blank_lines: &mut Vec<bool>) {
+    let mut nodes: Vec<(bool, KdlNode)> = std::mem::take(document.nodes_mut())
+        .into_iter()
+        .enumerate()
+        .map(|(index, node)| (index > 0 && has_blank_line(&node), node))
+        .collect();
+
+    nodes.sort_by_key(|(_, node)| section(block, node));
+
+    for (index, (blank_line, node)) in nodes.iter_mut().enumerate() {
+        blank_lines.push(index > 0 && *blank_line);
+
+        let child_block = block.child(node.name().value());
+        rename(node, block);
+        requote(node);
+
+        if let (Some(child_block), Some(children)) = (child_block, node.children_mut()) {
+            prepare(children, child_block, blank_lines);
+        }
+    }
+
+    *document.nodes_mut() = nodes.into_iter().map(|(_, node)| node).collect();
+}
+
+fn space(document: &mut KdlDocument, block: Block, blank_lines: &mut impl Iterator<Item = bool>) {
+    let mut previous: Option<String> = None;
+    for node in document.nodes_mut() {
+        let name = String::from(node.name().value());
+        let blank_line = blank_lines.next().unwrap_or(false);
+
+        let separate = match (block, previous.as_deref()) {
+            (_, None) => false,
+            (Block::Document, Some("import")) => blank_line || name != "import",
+            (Block::Document | Block::Tasks, _) => true,
+            (Block::Task, Some(previous)) => {
+                blank_line || (!is_action(previous) && is_action(&name))
+            }
+            _ => blank_line,
+        };
+
+        if separate {
+            super::blank_line_before(node);
+        }
+
+        if let (Some(child_block), Some(children)) = (block.child(&name), node.children_mut()) {
+            space(children, child_block, blank_lines);
+        }
+
+        previous = Some(name);
+    }
+}
+
+/// Rank of a node in the canonical section order of its block. Nodes of equal rank keep
+/// their relative order. Imports merge their variables in the order the file is read, so
+/// `import`, `default` and `variables` share a rank and stay where the author put them
+fn section(block: Block, node: &KdlNode) -> usize {
+    match (block, block.canonical(node.name().value())) {
+        (Block::Document, "import" | "default" | "variables") => 0,
+        (Block::Document, "tasks") => 1,
+        (Block::Document, _) => 2,
+        (Block::Task, "alias") => 0,
+        (Block::Task, "variables") => 1,
+        (Block::Task, "requires") => 2,
+        (Block::Task, _) => 3,
+        _ => 0,
+    }
+}
+
+fn rename(node: &mut KdlNode, block: Block) {
+    let name = match block.canonical(node.name().value()) {
+        "alias" if block == Block::Task && node.entries().len() > 1 => "aliases",
+        name => name,
+    };
+
+    if name != node.name().value() {
+        let name = String::from(name);
+        node.set_name(name);
+    }
+}
+
+/// Writes every string as a quoted string. Raw and multi-line strings are left as written
+fn requote(node: &mut KdlNode) {
+    for entry in node.entries_mut() {
+        let KdlValue::String(value) = entry.value() else {
+            entry.clear_format();
+            continue;
+        };
+
+        let repr = match entry.format() {
+            Some(format) if format.value_repr.starts_with('#') => format.value_repr.clone(),
+            Some(format) if format.value_repr.starts_with("\"\"\"") => format.value_repr.clone(),
+            _ => quote(value),
+        };
+
+        let mut format = entry.format().cloned().unwrap_or_default();
+        format.value_repr = repr;
+        format.leading = String::from(" ");
+        format.autoformat_keep = true;
+        entry.set_format(format);
+    }
+}
+
+/// True when there is an empty line between the node and whatever precedes it
+fn has_blank_line(node: &KdlNode) -> bool {
+    let Some(format) = node.format() else {
+        return false;
+    };
+
+    let whitespace = format
+        .leading
+        .find(|c: char| !c.is_whitespace())
+        .unwrap_or(format.leading.len());
+
+    format.leading[..whitespace].contains('\n')
+}
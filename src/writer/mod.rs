@@ -0,0 +1,760 @@
+//! Turns the task file model back into KDL.
+//!
+//! [`write`] renders a task file from scratch, [`update`] applies a task file to an existing
+//! document and only touches the nodes that changed, so comments, ordering and formatting
+//! survive a round trip:
+//!
+//! ```
+//! use jatr::{reader, writer};
+//!
+//! let source = "// Build everything\ntasks {\n    build {\n        cmd \"cargo build\" // debug\n    }\n}\n";
+//! let mut document = source.parse().unwrap();
+//! let mut task_file = reader::read(String::from(source)).unwrap();
+//!
+//! task_file.tasks["build"].aliases.push(String::from("b"));
+//! writer::update(&mut document, &task_file);
+//!
+//! assert_eq!(
+//!     document.to_string(),
+//!     "// Build everything\ntasks {\n    build {\n        alias \"b\"\n        cmd \"cargo build\" // debug\n    }\n}\n"
+//! );
+//! ```
+
+mod format;
+
+pub use format::format;
+
+use crate::tasks::{
+    Action, ActionCommand, FileAction, Import, MessageLevel, Requirement, RequirementKind, Task,
+    TaskFile, Value, Variable, VariableValue,
+};
+use crate::utils::format_duration;
+use kdl::{
+    FormatConfig, KdlDocument, KdlDocumentFormat, KdlEntry, KdlEntryFormat, KdlNode, KdlNodeFormat,
+    KdlValue,
+};
+use std::fmt::Write;
+
+/// Renders the task file as a freshly formatted document. Imported tasks and variables are
+/// not inlined, the `import` nodes they came from are written instead
+pub fn write(task_file: &TaskFile) -> KdlDocument {
+    let mut document = KdlDocument::new();
+    let nodes = document.nodes_mut();
+
+    for import in &task_file.imports {
+        nodes.push(import_node(import));
+    }
+
+    if let Some(default) = &task_file.default {
+        let mut node = KdlNode::new("default");
+        node.push(string_entry(default));
+        nodes.push(node);
+    }
+
+    let variables: Vec<&Variable> = task_file
+        .variables
+        .iter()
+        .filter(|variable| variable.source.is_none())
+        .collect();
+    if !variables.is_empty() {
+        nodes.push(variables_node(variables));
+    }
+
+    let tasks: Vec<&Task> = task_file
+        .tasks
+        .values()
+        .filter(|task| task.source.is_none())
+        .collect();
+    if !tasks.is_empty() {
+        let mut node = KdlNode::new("tasks");
+        let children = node.ensure_children();
+        for task in tasks {
+            children.nodes_mut().push(raw_task_node(task));
+        }
+        nodes.push(node);
+    }
+
+    for node in nodes.iter_mut() {
+        format_node(node, 0);
+    }
+
+    for (index, node) in nodes.iter_mut().enumerate() {
+        let after_import = index > 0 && node.name().value() == "import";
+        if index > 0 && !after_import {
+            blank_line_before(node);
+        }
+
+        if node.name().value() == "tasks" {
+            if let Some(children) = node.children_mut() {
+                for (index, task) in children.nodes_mut().iter_mut().enumerate() {
+                    space_task_body(task);
+                    if index > 0 {
+                        blank_line_before(task);
+                    }
+                }
+            }
+        }
+    }
+
+    document
+}
+
+/// Updates `document` in place to describe `task_file`. Nodes that did not change are left
+/// untouched and changed nodes keep their comments and spelling where possible
+pub fn update(document: &mut KdlDocument, task_file: &TaskFile) {
+    merge_documents(document, write(task_file), Block::Document, 0);
+}
+
+/// Builds a formatted task node. Call `autoformat_config` with an indent level to nest it
+pub fn task_node(task: &Task) -> KdlNode {
+    let mut node = raw_task_node(task);
+    format_node(&mut node, 0);
+    space_task_body(&mut node);
+    node
+}
+
+/// Builds a formatted action node, `None` for actions that have no KDL representation
+pub fn action_node(action: &Action) -> Option<KdlNode> {
+    let mut node = raw_action_node(action)?;
+    format_node(&mut node, 0);
+    Some(node)
+}
+
+/// Builds a formatted node for an entry of a `variables` block
+pub fn variable_node(variable: &Variable) -> KdlNode {
+    let mut node = raw_variable_node(variable);
+    format_node(&mut node, 0);
+    node
+}
+
+fn import_node(import: &Import) -> KdlNode {
+    let mut node = KdlNode::new("import");
+    node.push(string_entry(&import.path));
+    if let Some(prefix) = &import.prefix {
+        node.push(string_property("prefix", prefix));
+    }
+    if let Some(format) = &import.format {
+        node.push(string_property("format", format.name()));
+    }
+
+    node
+}
+
+fn variables_node(variables: Vec<&Variable>) -> KdlNode {
+    let mut node = KdlNode::new("variables");
+    let children = node.ensure_children();
+    for variable in variables {
+        children.nodes_mut().push(raw_variable_node(variable));
+    }
+
+    node
+}
+
+fn raw_task_node(task: &Task) -> KdlNode {
+    let mut node = KdlNode::new(task.name.as_str());
+    if let Some(description) = &task.description {
+        node.push(string_entry(description));
+    }
+
+    if task.private != task.name.starts_with('_') {
+        node.push(KdlEntry::new_prop("private", task.private));
+    }
+
+    let mut children = vec![];
+    if !task.aliases.is_empty() {
+        let name = match task.aliases.len() {
+            1 => "alias",
+            _ => "aliases",
+        };
+        let mut aliases = KdlNode::new(name);
+        for alias in &task.aliases {
+            aliases.push(string_entry(alias));
+        }
+        children.push(aliases);
+    }
+
+    if !task.variables.is_empty() {
+        children.push(variables_node(task.variables.iter().collect()));
+    }
+
+    if !task.requirements.is_empty() {
+        let mut requires = KdlNode::new("requires");
+        let nodes = requires.ensure_children().nodes_mut();
+        nodes.extend(task.requirements.iter().map(requirement_node));
+        children.push(requires);
+    }
+
+    children.extend(task.actions.iter().filter_map(raw_action_node));
+
+    if !children.is_empty() {
+        node.ensure_children().nodes_mut().extend(children);
+    }
+
+    node
+}
+
+fn requirement_node(requirement: &Requirement) -> KdlNode {
+    let mut node = match &requirement.kind {
+        RequirementKind::Tool { name, version } => {
+            let mut node = KdlNode::new("tool");
+            node.push(string_entry(name));
+            if let Some(version) = version {
+                node.push(string_property("version", version));
+            }
+            node
+        }
+        RequirementKind::Env(name) => {
+            let mut node = KdlNode::new("env");
+            node.push(string_entry(name));
+            node
+        }
+        RequirementKind::File(path) => {
+            let mut node = KdlNode::new("file");
+            node.push(string_entry(path));
+            node
+        }
+    };
+
+    if let Some(hint) = &requirement.hint {
+        node.push(string_property("hint", hint));
+    }
+
+    node
+}
+
+/// Separates the actions of a formatted task from its aliases, variables and requirements
+fn space_task_body(task: &mut KdlNode) {
+    if let Some(children) = task.children_mut() {
+        let first_action = children
+            .nodes()
+            .iter()
+            .position(|node| is_action(node.name().value()));
+
+        if let Some(index) = first_action.filter(|index| *index > 0) {
+            blank_line_before(&mut children.nodes_mut()[index]);
+        }
+    }
+}
+
+fn is_action(name: &str) -> bool {
+    !matches!(
+        Block::Task.canonical(name),
+        "alias" | "variables" | "requires"
+    )
+}
+
+fn raw_action_node(action: &Action) -> Option<KdlNode> {
+    let node = match action {
+        Action::Noop => return None,
+        Action::Command(cmd) => command_node("cmd", cmd),
+        Action::If(cmd) => command_node("if", cmd),
+        Action::Task(call) => {
+            let mut node = KdlNode::new("task");
+            node.push(string_entry(&call.name));
+            node
+        }
+        Action::Cd(path) => {
+            let mut node = KdlNode::new("cd");
+            node.push(string_entry(path));
+            node
+        }
+        Action::Confirm(message) => {
+            let mut node = KdlNode::new("confirm");
+            node.push(string_entry(message));
+            node
+        }
+        Action::Echo(message) => {
+            let mut node = KdlNode::new("echo");
+            node.push(string_entry(&message.text));
+            if message.level != MessageLevel::Info {
+                node.push(string_property("level", message.level.name()));
+            }
+            node
+        }
+        Action::Fail(failure) => {
+            let mut node = KdlNode::new("fail");
+            node.push(string_entry(&failure.message));
+            if failure.code != 1 {
+                node.push(KdlEntry::new_prop("code", failure.code as i128));
+            }
+            node
+        }
+        Action::File(action) => {
+            let mut node = KdlNode::new(action.name());
+            for template in action.templates() {
+                node.push(string_entry(template));
+            }
+            if let FileAction::Chmod { mode, .. } = action {
+                node.push(string_entry(&format!("{:o}", mode)));
+            }
+            node
+        }
+        Action::Script(script) => {
+            let mut node = KdlNode::new("script");
+            node.push(string_entry(&script.body));
+            if let Some(interpreter) = &script.interpreter {
+                node.push(string_property("interpreter", interpreter));
+            }
+            node
+        }
+    };
+
+    Some(node)
+}
+
+fn command_node(name: &str, cmd: &ActionCommand) -> KdlNode {
+    let mut node = KdlNode::new(name);
+    node.push(string_entry(&cmd.command));
+    if let Some(shell) = &cmd.shell {
+        node.push(string_property("shell", shell));
+    }
+
+    node
+}
+
+fn raw_variable_node(variable: &Variable) -> KdlNode {
+    let mut node = KdlNode::new(variable.name.as_str());
+    let constraints = &variable.constraints;
+
+    match &variable.value {
+        VariableValue::Static(Value::Null) if constraints.required => {}
+        VariableValue::Static(value) => push_value(&mut node, value),
+        VariableValue::Action(action) => {
+            if let Some(action) = raw_action_node(action) {
+                node.ensure_children().nodes_mut().push(action);
+            }
+        }
+        VariableValue::File(path) => node.push(string_property("from_file", path)),
+        VariableValue::Env(name) => node.push(string_property("from_env", name)),
+        VariableValue::Prompt(prompt) => {
+            let mut prompt_node = KdlNode::new(prompt.kind.name());
+            prompt_node.push(string_entry(&prompt.message));
+            if let Some(default) = &prompt.default {
+                prompt_node.push(string_property("default", default));
+            }
+            node.ensure_children().nodes_mut().push(prompt_node);
+        }
+    }
+
+    if let Some(value_type) = constraints.value_type {
+        node.push(string_property("type", value_type.name()));
+    }
+    if constraints.required {
+        node.push(KdlEntry::new_prop("required", true));
+    }
+    if let Some(pattern) = &constraints.pattern {
+        node.push(string_property("pattern", pattern));
+    }
+    if variable.secret {
+        node.push(KdlEntry::new_prop("secret", true));
+    }
+    if let Some(cache) = variable.cache {
+        node.push(string_property("cache", &format_duration(cache)));
+    }
+    if !constraints.choices.is_empty() {
+        let mut choices = KdlNode::new("enum");
+        choices
+            .entries_mut()
+            .extend(constraints.choices.iter().filter_map(value_entry));
+        node.ensure_children().nodes_mut().insert(0, choices);
+    }
+
+    node
+}
+
+/// Writes a value as the argument of a node, lists as `-` children and maps as named children
+fn push_value(node: &mut KdlNode, value: &Value) {
+    let items: Vec<(&str, &Value)> = match value {
+        Value::List(items) => items.iter().map(|item| ("-", item)).collect(),
+        Value::Map(map) => map.iter().map(|(key, item)| (key.as_str(), item)).collect(),
+        value => {
+            if let Some(entry) = value_entry(value) {
+                node.push(entry);
+            }
+            return;
+        }
+    };
+
+    let children = node.ensure_children();
+    for (name, item) in items {
+        let mut item_node = KdlNode::new(name);
+        push_value(&mut item_node, item);
+        children.nodes_mut().push(item_node);
+    }
+}
+
+/// Converts a scalar value into an entry. Lists and maps have no inline representation
+fn value_entry(value: &Value) -> Option<KdlEntry> {
+    let entry = match value {
+        Value::String(s) => string_entry(s),
+        Value::Int(i) => KdlEntry::new(KdlValue::Integer(*i as i128)),
+        Value::Float(f) => KdlEntry::new(*f),
+        Value::Bool(b) => KdlEntry::new(*b),
+        Value::Null => KdlEntry::new(KdlValue::Null),
+        Value::List(_) | Value::Map(_) => return None,
+    };
+
+    Some(entry)
+}
+
+/// String argument that is always written quoted, even when it would be a valid identifier
+pub(crate) fn string_entry(value: &str) -> KdlEntry {
+    let mut entry = KdlEntry::new(value);
+    entry.set_format(quoted_format(value));
+    entry
+}
+
+pub(crate) fn string_property(key: &str, value: &str) -> KdlEntry {
+    let mut entry = KdlEntry::new_prop(key, value);
+    entry.set_format(quoted_format(value));
+    entry
+}
+
+fn quoted_format(value: &str) -> KdlEntryFormat {
+    KdlEntryFormat {
+        value_repr: quote(value),
+        leading: String::from(" "),
+        autoformat_keep: true,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{{{:x}}}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+/// Formats a node as if it was nested `depth` levels deep
+pub(crate) fn format_node(node: &mut KdlNode, depth: usize) {
+    ensure_format(node);
+    node.autoformat_config(&FormatConfig::builder().indent_level(depth).build());
+}
+
+/// Autoformat only indents nodes and children blocks that already carry a format, and only
+/// keeps entry formats flagged to be kept, which quoted strings rely on
+fn ensure_format(node: &mut KdlNode) {
+    if node.format().is_none() {
+        node.set_format(KdlNodeFormat::default());
+    }
+
+    for entry in node.entries_mut() {
+        entry.keep_format();
+    }
+
+    if let Some(children) = node.children_mut() {
+        if children.format().is_none() {
+            children.set_format(KdlDocumentFormat::default());
+        }
+        for child in children.nodes_mut() {
+            ensure_format(child);
+        }
+    }
+}
+
+fn blank_line_before(node: &mut KdlNode) {
+    if let Some(format) = node.format_mut() {
+        if !format.leading.starts_with('\n') {
+            format.leading.insert(0, '\n');
+        }
+    }
+}
+
+/// Kind of block a node appears in, which decides how its name is spelled and how it is
+/// matched up when merging
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Block {
+    Document,
+    Variables,
+    Variable,
+    Tasks,
+    Task,
+    Actions,
+    Requires,
+}
+
+impl Block {
+    /// Block formed by the children of a node named `name` in this block
+    pub(crate) fn child(&self, name: &str) -> Option<Block> {
+        match (self, name) {
+            (Block::Document | Block::Task, "variables" | "vars") => Some(Block::Variables),
+            (Block::Document, "tasks") => Some(Block::Tasks),
+            (Block::Variables, _) => Some(Block::Variable),
+            (Block::Tasks, _) => Some(Block::Task),
+            (Block::Task, "actions") => Some(Block::Actions),
+            (Block::Task, "requires") => Some(Block::Requires),
+            _ => None,
+        }
+    }
+
+    /// Canonical spelling of a node name in this block
+    pub(crate) fn canonical<'a>(&self, name: &'a str) -> &'a str {
+        match (self, name) {
+            (Block::Document | Block::Task, "vars") => "variables",
+            (Block::Task | Block::Actions, "-") => "cmd",
+            (Block::Task, "aliases") => "alias",
+            _ => name,
+        }
+    }
+
+    /// Nodes in keyed blocks are matched by name, everywhere else order carries meaning
+    fn keyed(&self) -> bool {
+        matches!(self, Block::Document | Block::Variables | Block::Tasks)
+    }
+
+    fn key(&self, node: &KdlNode) -> String {
+        match self.canonical(node.name().value()) {
+            "import" if *self == Block::Document => format!(
+                "import {}",
+                node.get(0).map(|path| path.to_string()).unwrap_or_default()
+            ),
+            name => String::from(name),
+        }
+    }
+}
+
+/// Node text with formatting removed and names spelled canonically
+fn normalized(node: &KdlNode, block: Block) -> String {
+    let mut node = node.clone();
+    normalize_names(&mut node, block);
+    node.clear_format_recursive();
+    node.to_string()
+}
+
+fn normalize_names(node: &mut KdlNode, block: Block) {
+    let name = node.name().value().to_string();
+    node.set_name(block.canonical(&name));
+
+    if let (Some(child_block), Some(children)) = (block.child(&name), node.children_mut()) {
+        for child in children.nodes_mut() {
+            normalize_names(child, child_block);
+        }
+    }
+}
+
+fn merge_documents(old: &mut KdlDocument, new: KdlDocument, block: Block, depth: usize) {
+    let old_nodes = std::mem::take(old.nodes_mut());
+    let new_nodes = new.nodes().to_vec();
+
+    let pairs = match block.keyed() {
+        true => keyed_pairs(&old_nodes, &new_nodes, block),
+        false => sequence_pairs(&old_nodes, &new_nodes, block),
+    };
+
+    // Nodes without a counterpart in the old document go right after the old node their
+    // predecessor was matched with
+    let mut kept: Vec<Option<KdlNode>> = vec![None; old_nodes.len()];
+    let mut inserted: Vec<Vec<KdlNode>> = vec![vec![]; old_nodes.len() + 1];
+    let mut anchor = 0;
+    for (new_node, pair) in new_nodes.into_iter().zip(pairs) {
+        match pair {
+            Some(index) => {
+                let mut node = old_nodes[index].clone();
+                merge_node(&mut node, new_node, block, depth);
+                kept[index] = Some(node);
+                anchor = index + 1;
+            }
+            None => inserted[anchor].push(new_node),
+        }
+    }
+
+    // The line break after an opening brace is part of the first node's leading whitespace,
+    // so it moves along when something is inserted in front of that node
+    if let (Some(first), Some(Some(old_first))) = (inserted[0].first_mut(), kept.first_mut()) {
+        if let (Some(format), Some(old_format)) = (first.format_mut(), old_first.format_mut()) {
+            let opening = match old_format.leading.find('\n') {
+                Some(end) if old_format.leading[..end].trim().is_empty() => end + 1,
+                _ => 0,
+            };
+            let opening: String = old_format.leading.drain(..opening).collect();
+            format.leading = format!("{}{}", opening, format.leading.trim_start_matches('\n'));
+        }
+    }
+
+    let mut inserted = inserted.into_iter();
+    let mut nodes: Vec<KdlNode> = inserted.next().unwrap_or_default();
+    for (node, after) in kept.into_iter().zip(inserted) {
+        nodes.extend(node);
+        nodes.extend(after);
+    }
+
+    let last = nodes.len().saturating_sub(1);
+    for node in nodes.iter_mut().take(last) {
+        if let Some(format) = node.format_mut() {
+            if format.terminator.is_empty() {
+                format.terminator = String::from("\n");
+            }
+        }
+    }
+
+    *old.nodes_mut() = nodes;
+}
+
+/// Matches every new node to the first unclaimed old node with the same key. In blocks named
+/// by the user, nodes left over between two matches are paired up in order as renamed
+fn keyed_pairs(old: &[KdlNode], new: &[KdlNode], block: Block) -> Vec<Option<usize>> {
+    let mut claimed = vec![false; old.len()];
+    let mut pairs: Vec<Option<usize>> = new
+        .iter()
+        .map(|node| {
+            let key = block.key(node);
+            let index = (0..old.len()).find(|i| !claimed[*i] && block.key(&old[*i]) == key)?;
+            claimed[index] = true;
+            Some(index)
+        })
+        .collect();
+
+    if !matches!(block, Block::Variables | Block::Tasks) {
+        return pairs;
+    }
+
+    let mut start = 0;
+    for index in 0..new.len() {
+        if let Some(matched) = pairs[index] {
+            start = matched + 1;
+            continue;
+        }
+
+        let end = pairs[index..].iter().flatten().next().copied();
+        let renamed = (start..end.unwrap_or(old.len())).find(|i| !claimed[*i]);
+        if let Some(renamed) = renamed {
+            claimed[renamed] = true;
+            pairs[index] = Some(renamed);
+            start = renamed + 1;
+        }
+    }
+
+    pairs
+}
+
+/// Matches the longest common subsequence of equivalent nodes, then pairs up the
+/// remaining nodes between two matches as modifications of each other
+fn sequence_pairs(old: &[KdlNode], new: &[KdlNode], block: Block) -> Vec<Option<usize>> {
+    let old_text: Vec<String> = old.iter().map(|node| normalized(node, block)).collect();
+    let new_text: Vec<String> = new.iter().map(|node| normalized(node, block)).collect();
+
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = match old_text[i] == new_text[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut pairs = vec![None; new.len()];
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_old, mut gap_new) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old_text[i] == new_text[j] {
+            pair_gap(&mut pairs, gap_old..i, gap_new..j);
+            pairs[j] = Some(i);
+            i += 1;
+            j += 1;
+            (gap_old, gap_new) = (i, j);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pair_gap(&mut pairs, gap_old..old.len(), gap_new..new.len());
+
+    pairs
+}
+
+fn pair_gap(pairs: &mut [Option<usize>], old: std::ops::Range<usize>, new: std::ops::Range<usize>) {
+    for (old_index, new_index) in old.zip(new) {
+        pairs[new_index] = Some(old_index);
+    }
+}
+
+fn merge_node(old: &mut KdlNode, mut new: KdlNode, block: Block, depth: usize) {
+    if normalized(old, block) == normalized(&new, block) {
+        return;
+    }
+
+    if block.canonical(old.name().value()) != block.canonical(new.name().value()) {
+        old.set_name(new.name().clone());
+    }
+
+    let old_entries = old.entries().to_vec();
+    let mut argument = 0;
+    let entries = new
+        .entries()
+        .iter()
+        .map(|entry| {
+            let previous = match entry.name() {
+                Some(name) => old_entries
+                    .iter()
+                    .find(|e| e.name().map(|n| n.value()) == Some(name.value())),
+                None => {
+                    argument += 1;
+                    old_entries
+                        .iter()
+                        .filter(|e| e.name().is_none())
+                        .nth(argument - 1)
+                }
+            };
+
+            match previous {
+                Some(previous) if previous.value() == entry.value() => previous.clone(),
+                _ => entry.clone(),
+            }
+        })
+        .collect();
+    *old.entries_mut() = entries;
+
+    let child_block = block.child(new.name().value());
+    match (new.children_mut().take(), child_block, old.children_mut()) {
+        (None, _, children) => *children = None,
+        (Some(mut new_children), Some(child_block), Some(old_children)) => {
+            if child_block == Block::Task {
+                shape_actions(old_children, &mut new_children, depth + 1);
+            }
+            merge_documents(old_children, new_children, child_block, depth + 1);
+        }
+        (Some(new_children), _, children) => *children = Some(new_children),
+    }
+}
+
+/// Moves generated actions into an `actions` block when the old task was written that way
+fn shape_actions(old: &KdlDocument, new: &mut KdlDocument, depth: usize) {
+    if !old
+        .nodes()
+        .iter()
+        .any(|node| node.name().value() == "actions")
+    {
+        return;
+    }
+
+    let nodes = std::mem::take(new.nodes_mut());
+    let (actions, mut others): (Vec<KdlNode>, Vec<KdlNode>) = nodes
+        .into_iter()
+        .partition(|node| is_action(node.name().value()));
+
+    if !actions.is_empty() {
+        let mut node = KdlNode::new("actions");
+        node.ensure_children().nodes_mut().extend(actions);
+        format_node(&mut node, depth);
+        if !others.is_empty() {
+            blank_line_before(&mut node);
+        }
+        others.push(node);
+    }
+
+    *new.nodes_mut() = others;
+}
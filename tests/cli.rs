@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const TASKS: &str = r#"
+tasks {
+    validate {
+        - "echo mine > ran.txt"
+    }
+
+    format {
+        alias "fmt"
+        - "echo formatted"
+    }
+}
+"#;
+
+fn jatr(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_jatr"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn validate_reports_tasks_shadowed_by_built_in_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("tasks.kdl"), TASKS).unwrap();
+
+    let output = jatr(dir.path(), &["validate"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("'validate' of task 'validate' is a built-in command"));
+    assert!(stdout.contains("'fmt' of task 'format' is a built-in command"));
+    assert!(!dir.path().join("ran.txt").exists());
+}
+
+#[test]
+fn run_reaches_tasks_shadowed_by_built_in_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("tasks.kdl"), TASKS).unwrap();
+
+    let output = jatr(dir.path(), &["run", "validate"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("run the task with `jatr run validate`"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("ran.txt")).unwrap(),
+        "mine\n"
+    );
+}
+
+#[test]
+fn aliases_shadowed_by_built_in_commands_are_left_out() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("tasks.kdl"), TASKS).unwrap();
+
+    let output = jatr(dir.path(), &["format"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("formatted"));
+}
+
+#[test]
+fn positional_assignments_and_var_override_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("tasks.kdl"),
+        r#"
+        variables {
+            greeting "hello"
+            name "world"
+        }
+
+        tasks {
+            greet {
+                - "echo {{ greeting }} {{ name }} > greeting.txt"
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    let greeting = || fs::read_to_string(dir.path().join("greeting.txt")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_jatr"))
+        .args(["greet", "--var", "greeting=hi", "name=jatr"])
+        .env("JATR_VAR_greeting", "hey")
+        .env("JATR_VAR_name", "you")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(greeting(), "hi jatr\n");
+
+    let output = jatr(dir.path(), &["greet", "unknown=value"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Variable 'unknown' is not defined in the task file"));
+}
+
+#[test]
+fn secret_values_are_masked_in_printed_commands_and_debug_logs() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("tasks.kdl"),
+        r#"
+        variables {
+            token secret=#true {
+                cmd "echo s3cr3t-value"
+            }
+        }
+
+        tasks {
+            deploy {
+                - "test -n {{ token }}"
+            }
+        }
+        "#,
+    )
+    .unwrap();
+
+    let output = jatr(dir.path(), &["-v", "deploy"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stdout.contains("test -n ***"), "{}", stdout);
+    assert!(stderr.contains("Executed: test -n ***"), "{}", stderr);
+    assert!(stderr.contains("Stdout: '***"), "{}", stderr);
+    assert!(!stdout.contains("s3cr3t-value"), "{}", stdout);
+    assert!(!stderr.contains("s3cr3t-value"), "{}", stderr);
+}
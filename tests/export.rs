@@ -0,0 +1,127 @@
+use jatr::export::{export, ExportFormat};
+use jatr::{reader, TaskFile};
+
+fn read(content: &str) -> TaskFile {
+    reader::read(String::from(content)).unwrap()
+}
+
+#[test]
+fn private_tasks_needing_jatr_are_run_by_their_callers() {
+    let task_file = read(
+        r#"
+        tasks {
+            ci {
+                task "lint"
+                - "cargo test"
+            }
+
+            lint private=#true {
+                if "test -f Cargo.toml"
+                - "cargo clippy"
+            }
+        }
+        "#,
+    );
+
+    for format in [
+        ExportFormat::Make,
+        ExportFormat::GithubActions,
+        ExportFormat::GitlabCi,
+    ] {
+        let exported = export(&task_file, format, "jatr");
+
+        assert!(exported.contains("jatr ci"), "{}", exported);
+        assert!(!exported.contains("jatr lint"), "{}", exported);
+    }
+}
+
+#[test]
+fn private_tasks_are_translated_like_public_ones() {
+    let task_file = read(
+        r#"
+        tasks {
+            ci {
+                task "lint"
+                - "cargo test"
+            }
+
+            lint private=#true {
+                - "cargo clippy"
+            }
+        }
+        "#,
+    );
+
+    let makefile = export(&task_file, ExportFormat::Make, "jatr");
+    assert!(
+        makefile.contains("ci: lint\n\tcargo test\n"),
+        "{}",
+        makefile
+    );
+    assert!(makefile.contains("lint:\n\tcargo clippy\n"), "{}", makefile);
+
+    let gitlab_ci = export(&task_file, ExportFormat::GitlabCi, "jatr");
+    assert!(
+        gitlab_ci.contains("ci:\n  script:\n  - cargo clippy\n  - cargo test\n"),
+        "{}",
+        gitlab_ci
+    );
+    assert!(!gitlab_ci.contains("lint:"), "{}", gitlab_ci);
+}
+
+#[test]
+fn requirements_are_checked_before_actions() {
+    let task_file = read(
+        r#"
+        tasks {
+            deploy {
+                requires {
+                    tool "docker" hint="Install Docker"
+                    env "DEPLOY_TOKEN"
+                    file "dist/app.tar"
+                }
+                - "docker push app"
+            }
+        }
+        "#,
+    );
+
+    let makefile = export(&task_file, ExportFormat::Make, "jatr");
+    assert!(
+        makefile.contains(concat!(
+            "deploy:\n",
+            "\tcommand -v docker >/dev/null || { echo 'tool '\\''docker'\\'' not found in PATH, hint: Install Docker' >&2; exit 1; }\n",
+            "\ttest -n \"$${DEPLOY_TOKEN+set}\" || { echo 'environment variable '\\''DEPLOY_TOKEN'\\'' is not set' >&2; exit 1; }\n",
+            "\ttest -e dist/app.tar || { echo 'file '\\''dist/app.tar'\\'' not found' >&2; exit 1; }\n",
+            "\tdocker push app\n",
+        )),
+        "{}",
+        makefile
+    );
+
+    let gitlab_ci = export(&task_file, ExportFormat::GitlabCi, "jatr");
+    assert!(
+        gitlab_ci.contains("  - test -n \"${DEPLOY_TOKEN+set}\" || { echo "),
+        "{}",
+        gitlab_ci
+    );
+}
+
+#[test]
+fn tool_versions_need_jatr() {
+    let task_file = read(
+        r#"
+        tasks {
+            build {
+                requires {
+                    tool "cargo" version=">=1.70"
+                }
+                - "cargo build"
+            }
+        }
+        "#,
+    );
+
+    let makefile = export(&task_file, ExportFormat::Make, "jatr");
+    assert!(makefile.contains("build:\n\tjatr build\n"), "{}", makefile);
+}
@@ -0,0 +1,101 @@
+use jatr::runner::executor::RecordingExecutor;
+use jatr::{reader, Runner, RunnerEnvironment, RunnerErrorData, RunnerResult};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Runs a task of the given actions in `dir`
+fn run(dir: &Path, actions: &str) -> Result<RunnerResult, String> {
+    let task_file = reader::read(format!("tasks {{ files {{ {} }} }}", actions)).unwrap();
+    let mut environment = RunnerEnvironment::default();
+    environment.work_dir(dir.to_str().unwrap()).unwrap();
+    let executor = RecordingExecutor::default();
+    let mut runner = Runner::with_executor(&task_file, environment, &executor);
+
+    runner
+        .run(task_file.find_task("files").unwrap())
+        .map_err(|e| match e.data() {
+            RunnerErrorData::Io(io) if io.kind() == ErrorKind::InvalidInput => {
+                String::from(e.message())
+            }
+            data => panic!("unexpected error {:?}: {}", data, e),
+        })
+}
+
+#[test]
+fn rejects_empty_paths() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for actions in [
+        r#"rm """#,
+        r#"rm "  ""#,
+        r#"mkdir "{{ missing | default(value='') }}""#,
+        r#"touch " ""#,
+        r#"copy "" "to""#,
+        r#"write "" "content""#,
+        r#"symlink "" "link""#,
+    ] {
+        let error = run(dir.path(), actions).unwrap_err();
+        assert!(error.contains("is empty"), "{}: {}", actions, error);
+    }
+}
+
+#[test]
+fn refuses_to_remove_or_replace_the_working_directory() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("project");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("file.txt"), "keep").unwrap();
+
+    for actions in [
+        r#"rm ".""#,
+        r#"rm "./""#,
+        r#"rm "src/..""#,
+        r#"rm "..""#,
+        r#"rm "/""#,
+        r#"move "." "../moved""#,
+        r#"move "file.txt" "..""#,
+        r#"copy "file.txt" ".""#,
+    ] {
+        let error = run(&dir, actions).unwrap_err();
+        assert!(
+            error.contains("is the working directory or contains it"),
+            "{}: {}",
+            actions,
+            error
+        );
+    }
+
+    assert_eq!(fs::read_to_string(dir.join("file.txt")).unwrap(), "keep");
+    assert!(dir.join("src").is_dir());
+}
+
+#[test]
+fn removes_moves_and_copies_inside_the_working_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    fs::create_dir_all(dir.join("build/out")).unwrap();
+    fs::write(dir.join("notes.txt"), "notes").unwrap();
+    fs::create_dir(dir.join("elsewhere")).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir, dir.join("elsewhere/here")).unwrap();
+
+    let result = run(
+        dir,
+        r#"
+        rm "build/out/.."
+        copy "notes.txt" "copy.txt"
+        move "copy.txt" "elsewhere/moved.txt"
+        rm "elsewhere/here"
+        "#,
+    );
+
+    assert_eq!(result, Ok(RunnerResult::Success));
+    assert!(!dir.join("build").exists());
+    assert_eq!(
+        fs::read_to_string(dir.join("elsewhere/moved.txt")).unwrap(),
+        "notes"
+    );
+    assert!(!dir.join("elsewhere/here").exists());
+    assert!(dir.join("notes.txt").exists());
+}
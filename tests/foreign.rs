@@ -0,0 +1,270 @@
+use camino::Utf8Path;
+use jatr::reader;
+use jatr::tasks::{Action, Task};
+use std::fs;
+use tempfile::TempDir;
+
+/// Imported task as `(name, aliases, private, description, actions)`, with `task` actions
+/// written as `task <name>` and `{dir}` standing for the directory of the imported file
+type Expected<'a> = (&'a str, &'a [&'a str], bool, Option<&'a str>, &'a [&'a str]);
+
+/// Imports `content` saved as `file` with the given format, prefixed with `x`. Tasks are
+/// returned with their keys, which carry the prefix
+fn import(file: &str, format: &str, content: &str) -> (TempDir, Vec<(String, Task)>) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = Utf8Path::from_path(dir.path()).unwrap().join(file);
+    fs::write(&path, content).unwrap();
+
+    let task_file = reader::read(format!(
+        r#"import "{}" prefix="x" format="{}""#,
+        path, format
+    ))
+    .unwrap();
+
+    (dir, task_file.tasks.into_iter().collect())
+}
+
+fn action(action: &Action) -> String {
+    match action {
+        Action::Command(command) => command.command.clone(),
+        Action::Task(call) => format!("task {}", call.name),
+        action => panic!("unexpected action {:?}", action),
+    }
+}
+
+fn check(dir: &TempDir, tasks: &[(String, Task)], expected: &[Expected]) {
+    let names: Vec<&str> = tasks.iter().map(|(key, _)| key.as_str()).collect();
+    let expected_names: Vec<&str> = expected.iter().map(|e| e.0).collect();
+    assert_eq!(names, expected_names);
+
+    for ((_, task), (name, aliases, private, description, actions)) in tasks.iter().zip(expected) {
+        assert_eq!(task.aliases, *aliases, "aliases of {}", name);
+        assert_eq!(task.private, *private, "private of {}", name);
+        assert_eq!(
+            task.description.as_deref(),
+            *description,
+            "description of {}",
+            name
+        );
+
+        let actions: Vec<String> = actions
+            .iter()
+            .map(|action| action.replace("{dir}", dir.path().to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            task.actions.iter().map(action).collect::<Vec<_>>(),
+            actions,
+            "actions of {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn package_json_scripts() {
+    let cases: &[(&str, &str, &[Expected])] = &[
+        (
+            "npm",
+            r#"{ "scripts": { "build": "tsc", "_clean": "rm -rf dist" } }"#,
+            &[
+                (
+                    "x:build",
+                    &[],
+                    false,
+                    Some("tsc"),
+                    &["npm --prefix {dir} run build"],
+                ),
+                (
+                    "x:_clean",
+                    &[],
+                    true,
+                    Some("rm -rf dist"),
+                    &["npm --prefix {dir} run _clean"],
+                ),
+            ],
+        ),
+        (
+            "yarn",
+            r#"{ "scripts": { "test:unit": "jest unit" } }"#,
+            &[(
+                "x:test:unit",
+                &[],
+                false,
+                Some("jest unit"),
+                &["yarn --cwd {dir} run test:unit"],
+            )],
+        ),
+        (
+            "pnpm",
+            r#"{ "scripts": { "lint": "eslint ." } }"#,
+            &[(
+                "x:lint",
+                &[],
+                false,
+                Some("eslint ."),
+                &["pnpm --dir {dir} run lint"],
+            )],
+        ),
+        ("npm", r#"{ "name": "no-scripts" }"#, &[]),
+    ];
+
+    for (format, content, expected) in cases {
+        let (dir, tasks) = import("package.json", format, content);
+        check(&dir, &tasks, expected);
+    }
+}
+
+#[test]
+fn makefile_targets() {
+    let cases: &[(&str, &[Expected])] = &[
+        (
+            "build: ## Builds the project\n\tcc -o app main.c\n",
+            &[(
+                "x:build",
+                &[],
+                false,
+                Some("Builds the project"),
+                &["make -C {dir} -f Makefile build"],
+            )],
+        ),
+        (
+            "app: lib main.o\n\tcc -o app\nlib:\n\tar rcs lib.a\n",
+            &[
+                (
+                    "x:app",
+                    &[],
+                    false,
+                    None,
+                    &["task x:lib", "make -C {dir} -f Makefile -o lib app"],
+                ),
+                (
+                    "x:lib",
+                    &[],
+                    false,
+                    None,
+                    &["make -C {dir} -f Makefile lib"],
+                ),
+            ],
+        ),
+        (
+            "%.o: %.c\n\tcc -c $<\n$(OUT): main.o\n\tcc\nall: main.o\n",
+            &[(
+                "x:all",
+                &[],
+                false,
+                None,
+                &["make -C {dir} -f Makefile all"],
+            )],
+        ),
+        (
+            ".PHONY: test _setup\ntest: _setup\n\tcargo test\n_setup:\n\ttrue\n",
+            &[
+                (
+                    "x:test",
+                    &[],
+                    false,
+                    None,
+                    &["task x:_setup", "make -C {dir} -f Makefile -o _setup test"],
+                ),
+                (
+                    "x:_setup",
+                    &[],
+                    true,
+                    None,
+                    &["make -C {dir} -f Makefile _setup"],
+                ),
+            ],
+        ),
+        (
+            "CC := gcc\ndefine RECIPE\nfake: target\nendef\nclean:\n\trm -f app\n",
+            &[(
+                "x:clean",
+                &[],
+                false,
+                None,
+                &["make -C {dir} -f Makefile clean"],
+            )],
+        ),
+    ];
+
+    for (content, expected) in cases {
+        let (dir, tasks) = import("Makefile", "make", content);
+        check(&dir, &tasks, expected);
+    }
+}
+
+#[test]
+fn justfile_recipes() {
+    let cases: &[(&str, &[Expected])] = &[
+        (
+            "# Builds everything\nbuild:\n    cargo build\n",
+            &[(
+                "x:build",
+                &[],
+                false,
+                Some("Builds everything"),
+                &["just --justfile {dir}/justfile --working-directory {dir} build"],
+            )],
+        ),
+        (
+            "alias b := build\nalias t := test\n\nbuild:\n    cargo build\n\ntest target='all': build\n    cargo test\n",
+            &[
+                (
+                    "x:build",
+                    &["x:b"],
+                    false,
+                    None,
+                    &["just --justfile {dir}/justfile --working-directory {dir} build"],
+                ),
+                (
+                    "x:test",
+                    &["x:t"],
+                    false,
+                    None,
+                    &["just --justfile {dir}/justfile --working-directory {dir} test"],
+                ),
+            ],
+        ),
+        (
+            "[private]\nhelper:\n    true\n\n_hidden:\n    true\n\n[linux]\nshown:\n    true\n",
+            &[
+                (
+                    "x:helper",
+                    &[],
+                    true,
+                    None,
+                    &["just --justfile {dir}/justfile --working-directory {dir} helper"],
+                ),
+                (
+                    "x:_hidden",
+                    &[],
+                    true,
+                    None,
+                    &["just --justfile {dir}/justfile --working-directory {dir} _hidden"],
+                ),
+                (
+                    "x:shown",
+                    &[],
+                    false,
+                    None,
+                    &["just --justfile {dir}/justfile --working-directory {dir} shown"],
+                ),
+            ],
+        ),
+        (
+            "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\nexport PATH := \"bin\"\n@quiet:\n    echo\n",
+            &[(
+                "x:quiet",
+                &[],
+                false,
+                None,
+                &["just --justfile {dir}/justfile --working-directory {dir} quiet"],
+            )],
+        ),
+    ];
+
+    for (content, expected) in cases {
+        let (dir, tasks) = import("justfile", "just", content);
+        check(&dir, &tasks, expected);
+    }
+}
@@ -0,0 +1,574 @@
+use jatr::runner::executor::RecordingExecutor;
+use jatr::runner::redact::Secrets;
+use jatr::tasks::Value;
+use jatr::{reader, Runner, RunnerEnvironment, RunnerErrorData, RunnerResult, TaskFile};
+
+fn read(content: &str) -> TaskFile {
+    reader::read(String::from(content)).unwrap()
+}
+
+fn run(task_file: &TaskFile, task: &str, executor: &RecordingExecutor) -> RunnerResult {
+    run_in(task_file, task, RunnerEnvironment::default(), executor)
+}
+
+fn run_in(
+    task_file: &TaskFile,
+    task: &str,
+    environment: RunnerEnvironment,
+    executor: &RecordingExecutor,
+) -> RunnerResult {
+    let mut runner = Runner::with_executor(task_file, environment, executor);
+    runner.run(task_file.find_task(task).unwrap()).unwrap()
+}
+
+#[test]
+fn runs_commands_in_order() {
+    let task_file = read(
+        r#"
+        tasks {
+            build {
+                - "cargo fmt --check"
+                cmd "cargo build --release"
+                - "cargo test"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(run(&task_file, "build", &executor), RunnerResult::Success);
+    assert_eq!(
+        executor.commands(),
+        vec!["cargo fmt --check", "cargo build --release", "cargo test"]
+    );
+}
+
+#[test]
+fn skips_remaining_actions_when_if_fails() {
+    let task_file = read(
+        r#"
+        tasks {
+            deploy {
+                - "make dist"
+                if "test -n \"$CI\""
+                - "make upload"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default().respond("test -n \"$CI\"", 1, "");
+
+    assert_eq!(run(&task_file, "deploy", &executor), RunnerResult::Skipped);
+    assert_eq!(executor.commands(), vec!["make dist", "test -n \"$CI\""]);
+}
+
+#[test]
+fn skips_remaining_actions_when_if_prints_false() {
+    let task_file = read(
+        r#"
+        tasks {
+            deploy {
+                if "test -f Cargo.lock && echo true || echo false"
+                - "make upload"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default().respond(
+        "test -f Cargo.lock && echo true || echo false",
+        0,
+        "false\n",
+    );
+
+    assert_eq!(run(&task_file, "deploy", &executor), RunnerResult::Skipped);
+    assert_eq!(
+        executor.commands(),
+        vec!["test -f Cargo.lock && echo true || echo false"]
+    );
+}
+
+#[test]
+fn runs_called_task_before_continuing() {
+    let task_file = read(
+        r#"
+        tasks {
+            release {
+                - "git tag v1"
+                task "build"
+                - "git push --tags"
+            }
+
+            build private=#true {
+                - "cargo build"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(run(&task_file, "release", &executor), RunnerResult::Success);
+    assert_eq!(
+        executor.commands(),
+        vec!["git tag v1", "cargo build", "git push --tags"]
+    );
+}
+
+#[test]
+fn stops_with_failure_when_command_exits_non_zero() {
+    let task_file = read(
+        r#"
+        tasks {
+            release {
+                task "test"
+                - "cargo publish"
+            }
+
+            test {
+                - "cargo test"
+                - "cargo clippy"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default().respond("cargo test", 101, "");
+
+    assert_eq!(run(&task_file, "release", &executor), RunnerResult::Failure);
+    assert_eq!(executor.commands(), vec!["cargo test"]);
+}
+
+#[test]
+fn fail_action_reports_its_exit_code() {
+    let task_file = read(
+        r#"
+        tasks {
+            check {
+                - "cargo check"
+                fail "not ready" code=3
+                - "cargo build"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+    let mut runner = Runner::with_executor(&task_file, RunnerEnvironment::default(), &executor);
+
+    let error = runner
+        .run(task_file.find_task("check").unwrap())
+        .unwrap_err();
+
+    assert!(matches!(error.data(), RunnerErrorData::Failed { code: 3 }));
+    assert_eq!(executor.commands(), vec!["cargo check"]);
+}
+
+#[test]
+fn cd_updates_cwd() {
+    let task_file = read(
+        r#"
+        tasks {
+            build {
+                cd "src"
+                - "echo {{ cwd }}"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(run(&task_file, "build", &executor), RunnerResult::Success);
+    assert!(executor.commands()[0].ends_with("/src"));
+}
+
+#[test]
+fn cd_keeps_task_file_cwd_variable() {
+    let task_file = read(
+        r#"
+        variables {
+            cwd "/srv/app"
+        }
+
+        tasks {
+            build {
+                cd "src"
+                - "echo {{ cwd }}"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(run(&task_file, "build", &executor), RunnerResult::Success);
+    assert_eq!(executor.commands(), vec!["echo /srv/app"]);
+}
+
+#[test]
+fn overridden_command_variable_does_not_run_its_command() {
+    let task_file = read(
+        r#"
+        variables {
+            version {
+                cmd "git describe --tags"
+            }
+        }
+
+        tasks {
+            release {
+                - "make release VERSION={{ version }}"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+    let mut environment = RunnerEnvironment::default();
+    environment.override_variable("version", "1.2.0");
+
+    assert_eq!(
+        run_in(&task_file, "release", environment, &executor),
+        RunnerResult::Success
+    );
+    assert_eq!(executor.commands(), vec!["make release VERSION=1.2.0"]);
+}
+
+#[test]
+fn overrides_take_precedence_over_environment_and_task_file() {
+    let task_file = read(
+        r#"
+        variables {
+            override_channel "stable"
+        }
+
+        tasks {
+            publish {
+                - "publish {{ override_channel }}"
+            }
+        }
+        "#,
+    );
+
+    let executor = RecordingExecutor::default();
+    run(&task_file, "publish", &executor);
+    std::env::set_var("JATR_VAR_override_channel", "beta");
+    run(&task_file, "publish", &executor);
+    let mut environment = RunnerEnvironment::default();
+    environment.override_variable("override_channel", "nightly");
+    run_in(&task_file, "publish", environment, &executor);
+    std::env::remove_var("JATR_VAR_override_channel");
+
+    assert_eq!(
+        executor.commands(),
+        vec!["publish stable", "publish beta", "publish nightly"]
+    );
+}
+
+#[test]
+fn unused_command_variables_are_not_resolved() {
+    let task_file = read(
+        r#"
+        variables {
+            commit {
+                cmd "git rev-parse HEAD"
+            }
+        }
+
+        tasks {
+            build {
+                vars {
+                    jobs {
+                        cmd "nproc"
+                    }
+                }
+
+                - "cargo build"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(run(&task_file, "build", &executor), RunnerResult::Success);
+    assert_eq!(executor.commands(), vec!["cargo build"]);
+}
+
+#[test]
+fn global_command_variable_runs_once_across_task_calls() {
+    let task_file = read(
+        r#"
+        variables {
+            version {
+                cmd "git describe --tags"
+            }
+        }
+
+        tasks {
+            release {
+                task "tag"
+                - "publish {{ version }}"
+            }
+
+            tag {
+                task "changelog"
+                - "git tag {{ version }}"
+            }
+
+            changelog {
+                - "changelog {{ version }}"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default().respond("git describe --tags", 0, "v1.2.0");
+
+    assert_eq!(run(&task_file, "release", &executor), RunnerResult::Success);
+    assert_eq!(
+        executor.commands(),
+        vec![
+            "git describe --tags",
+            "changelog v1.2.0",
+            "git tag v1.2.0",
+            "publish v1.2.0"
+        ]
+    );
+}
+
+#[test]
+fn builtin_variables_describe_the_run() {
+    let task_file = read(
+        r#"
+        tasks {
+            build "Builds it" {
+                alias "b"
+                - "{{ task.name }}: {{ task.description }} {{ task.aliases | join(sep=\",\") }}"
+                - "{{ args | join(sep=\" \") }} {{ cwd }} {{ task_file_dir }}"
+                - "{{ os }} {{ arch }} {{ env.JATR_TEST_BUILTIN }}"
+            }
+        }
+        "#,
+    );
+    std::env::set_var("JATR_TEST_BUILTIN", "set");
+    let mut environment = RunnerEnvironment::default();
+    environment.work_dir("/").unwrap();
+    environment.task_file_dir("/srv");
+    environment.args(vec![String::from("--release"), String::from("-v")]);
+    let executor = RecordingExecutor::default();
+
+    assert_eq!(
+        run_in(&task_file, "b", environment, &executor),
+        RunnerResult::Success
+    );
+    assert_eq!(
+        executor.commands(),
+        vec![
+            String::from("build: Builds it b"),
+            String::from("--release -v / /srv"),
+            format!("{} {} set", std::env::consts::OS, std::env::consts::ARCH),
+        ]
+    );
+}
+
+#[test]
+fn prompts_fail_when_input_is_not_interactive() {
+    let task_file = read(
+        r#"
+        variables {
+            region {
+                prompt "Which region?"
+            }
+        }
+
+        tasks {
+            deploy {
+                - "deploy {{ region }}"
+            }
+
+            drop {
+                confirm "Drop the database?"
+                - "dropdb app"
+            }
+        }
+        "#,
+    );
+
+    for (task, message) in [
+        (
+            "deploy",
+            "Set it with --var region=VALUE or JATR_VAR_region",
+        ),
+        ("drop", "Cannot confirm 'Drop the database?'"),
+    ] {
+        let executor = RecordingExecutor::default();
+        let mut environment = RunnerEnvironment::default();
+        environment.interactive(false);
+        let mut runner = Runner::with_executor(&task_file, environment, &executor);
+
+        let error = runner.run(task_file.find_task(task).unwrap()).unwrap_err();
+
+        assert!(matches!(error.data(), RunnerErrorData::InputRequired));
+        assert!(error.message().contains(message), "{}", error);
+        assert!(executor.commands().is_empty());
+    }
+}
+
+#[test]
+fn multiselect_override_is_split_on_commas() {
+    let task_file = read(
+        r#"
+        variables {
+            targets {
+                enum "linux" "macos" "windows"
+                multiselect "Build for?"
+            }
+        }
+
+        tasks {
+            build {
+                - "build {{ targets | join(sep=\" \") }} ({{ targets | length }})"
+            }
+        }
+        "#,
+    );
+
+    for (value, command) in [
+        ("linux,macos", "build linux macos (2)"),
+        ("windows", "build windows (1)"),
+        (",linux,,", "build linux (1)"),
+        ("", "build  (0)"),
+    ] {
+        let executor = RecordingExecutor::default();
+        let mut environment = RunnerEnvironment::default();
+        environment.interactive(false);
+        environment.override_variable("targets", value);
+
+        assert_eq!(
+            run_in(&task_file, "build", environment, &executor),
+            RunnerResult::Success
+        );
+        assert_eq!(executor.commands(), vec![command]);
+    }
+}
+
+#[test]
+fn secret_values_are_masked_in_check_errors() {
+    let task_file = read(
+        r#"
+        variables {
+            token "hunter22" secret=#true pattern="^ghp_"
+        }
+
+        tasks {
+            deploy {
+                - "deploy --token {{ token }}"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+    let mut runner = Runner::with_executor(&task_file, RunnerEnvironment::default(), &executor);
+
+    let error = runner
+        .run(task_file.find_task("deploy").unwrap())
+        .unwrap_err();
+
+    assert!(matches!(
+        error.data(),
+        RunnerErrorData::InvalidVariable { variable } if variable == "token"
+    ));
+    assert!(error.message().contains("***"), "{}", error);
+    assert!(!error.message().contains("hunter22"), "{}", error);
+    assert!(executor.commands().is_empty());
+}
+
+#[test]
+fn short_secret_values_are_not_masked() {
+    let mut secrets = Secrets::default();
+    secrets.add(&Value::String(String::from("1")));
+    secrets.add(&Value::String(String::from("abc\n")));
+    secrets.add(&Value::List(vec![
+        Value::Int(7),
+        Value::String(String::from("s3cret")),
+    ]));
+
+    assert_eq!(
+        secrets.redact("retry 1 of 7 with abc and s3cret"),
+        "retry 1 of 7 with abc and ***"
+    );
+}
+
+#[test]
+fn script_stops_at_failing_line_and_removes_its_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let task_file = read(
+        r#"
+        tasks {
+            setup {
+                script """
+                    echo "$0" > path.txt
+                    ls -l "$0" | cut -c1-10 > mode.txt
+                    false
+                    echo reached > after.txt
+                    """
+            }
+        }
+        "#,
+    );
+    let mut environment = RunnerEnvironment::default();
+    environment.work_dir(dir.path().to_str().unwrap()).unwrap();
+    let mut runner = Runner::for_taskfile(&task_file, environment);
+
+    let result = runner.run(task_file.find_task("setup").unwrap()).unwrap();
+
+    let read = |file: &str| std::fs::read_to_string(dir.path().join(file));
+    assert_eq!(result, RunnerResult::Failure);
+    assert!(read("after.txt").is_err());
+    assert_eq!(read("mode.txt").unwrap(), "-rw-------\n");
+    let script = read("path.txt").unwrap();
+    assert!(script.contains("jatr-setup-"), "{}", script);
+    assert!(!std::path::Path::new(script.trim_end()).exists());
+}
+
+#[test]
+fn requirements_of_called_tasks_are_checked_before_anything_runs() {
+    let task_file = read(
+        r#"
+        tasks {
+            release {
+                requires {
+                    env "JATR_TEST_MISSING_TOKEN"
+                }
+                - "make dist"
+                task "build"
+            }
+
+            build {
+                requires {
+                    file "missing/Cargo.toml" hint="Run it from the project root"
+                }
+                task "generate"
+                task "release"
+            }
+
+            generate {
+                requires {
+                    tool "jatr-test-missing-tool"
+                }
+                - "generate"
+            }
+        }
+        "#,
+    );
+    let executor = RecordingExecutor::default();
+    let mut runner = Runner::with_executor(&task_file, RunnerEnvironment::default(), &executor);
+
+    let error = runner
+        .run(task_file.find_task("release").unwrap())
+        .unwrap_err();
+
+    assert!(matches!(error.data(), RunnerErrorData::MissingRequirements));
+    assert_eq!(
+        error.message(),
+        "Task 'release' is missing requirements:\n  \
+         - environment variable 'JATR_TEST_MISSING_TOKEN' is not set\n  \
+         - task 'build': file 'missing/Cargo.toml' not found\n    \
+         hint: Run it from the project root\n  \
+         - task 'generate': tool 'jatr-test-missing-tool' not found in PATH"
+    );
+    assert!(executor.commands().is_empty());
+}
@@ -0,0 +1,43 @@
+use jatr::reader::{self, Format};
+use jatr::runner::executor::RecordingExecutor;
+use jatr::{Runner, RunnerEnvironment, RunnerResult};
+
+const YAML: &str = r#"
+tasks:
+  deploy:
+    actions:
+      - { if: echo false }
+      - make upload
+"#;
+
+const TOML: &str = r#"
+[tasks.deploy]
+actions = [{ if = "echo false" }, "make upload"]
+"#;
+
+const JSON: &str = r#"
+{ "tasks": { "deploy": { "actions": [{ "if": "echo false" }, "make upload"] } } }
+"#;
+
+#[test]
+fn if_printing_false_skips_next_action() {
+    for (content, format) in [
+        (YAML, Format::Yaml),
+        (TOML, Format::Toml),
+        (JSON, Format::Json),
+    ] {
+        let task_file = reader::read_format(String::from(content), format).unwrap();
+        let executor = RecordingExecutor::default().respond("echo false", 0, "false\n");
+        let mut runner = Runner::with_executor(&task_file, RunnerEnvironment::default(), &executor);
+
+        let result = runner.run(task_file.find_task("deploy").unwrap()).unwrap();
+
+        assert_eq!(result, RunnerResult::Skipped, "{:?}", format);
+        assert_eq!(executor.commands(), vec!["echo false"], "{:?}", format);
+        assert!(
+            !executor.requests()[0].tty,
+            "{:?} captures if output",
+            format
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use camino::Utf8Path;
+use jatr::reader::{self, Format, ParserErrorData};
+use jatr::tasks::{Action, Value, VariableValue};
+use jatr::validator::validate;
+use jatr::TaskFileReadError;
+use std::fs;
+
+const TASKS: &str = r#"
+default "b"
+
+tasks {
+    build {
+        alias "b"
+        - "cargo build"
+    }
+
+    test {
+        aliases "t" "check"
+        - "cargo test"
+    }
+}
+"#;
+
+fn duplicate_name(result: Result<jatr::TaskFile, TaskFileReadError>) -> String {
+    match result {
+        Err(TaskFileReadError::Parser(e, _)) => match e.data() {
+            ParserErrorData::DuplicateName { name } => name.clone(),
+            data => panic!("unexpected error {:?}", data),
+        },
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(_) => panic!("duplicate name was accepted"),
+    }
+}
+
+#[test]
+fn finds_tasks_by_name_and_alias() {
+    let task_file = reader::read(String::from(TASKS)).unwrap();
+
+    assert_eq!(task_file.find_task("build").unwrap().name, "build");
+    assert_eq!(task_file.find_task("b").unwrap().name, "build");
+    assert_eq!(task_file.find_task("check").unwrap().name, "test");
+    assert!(task_file.find_task("deploy").is_none());
+}
+
+#[test]
+fn default_names_a_task_or_alias() {
+    let task_file = reader::read(String::from(TASKS)).unwrap();
+
+    assert_eq!(task_file.default.as_deref(), Some("b"));
+    assert_eq!(task_file.find_task("b").unwrap().name, "build");
+}
+
+#[test]
+fn rejects_alias_of_another_task() {
+    let result = reader::read(String::from(
+        r#"tasks { build { alias "b"; }; bench { alias "b"; } }"#,
+    ));
+
+    assert_eq!(duplicate_name(result), "b");
+}
+
+#[test]
+fn rejects_alias_naming_another_task() {
+    let result = reader::read(String::from(
+        r#"tasks { build { - "cargo build"; }; compile { alias "build"; } }"#,
+    ));
+
+    assert_eq!(duplicate_name(result), "build");
+}
+
+#[test]
+fn rejects_task_defined_twice() {
+    let result = reader::read(String::from(r#"tasks { build; build; }"#));
+
+    assert_eq!(duplicate_name(result), "build");
+}
+
+#[test]
+fn rejects_alias_repeating_task_name() {
+    let result = reader::read(String::from(r#"tasks { build { aliases "b" "build"; } }"#));
+
+    assert_eq!(duplicate_name(result), "build");
+}
+
+#[test]
+fn rejects_duplicate_alias_in_structured_files() {
+    let result = reader::read_format(
+        String::from("tasks:\n  build: { aliases: [b] }\n  bench: { aliases: [b] }\n"),
+        Format::Yaml,
+    );
+
+    match result {
+        Err(TaskFileReadError::Structured(message)) => assert!(message.contains("'b'")),
+        _ => panic!("duplicate alias was accepted"),
+    }
+}
+
+#[test]
+fn rejects_names_clashing_after_import_prefixing() {
+    let dir = tempfile::tempdir().unwrap();
+    let imported = Utf8Path::from_path(dir.path()).unwrap().join("lib.kdl");
+    fs::write(&imported, r#"tasks { build { - "make"; } }"#).unwrap();
+
+    let result = reader::read(format!(
+        r#"
+        tasks {{ compile {{ alias "lib:build"; }} }}
+        import "{}" prefix="lib"
+        "#,
+        imported
+    ));
+
+    match result {
+        Err(TaskFileReadError::Parser(e, _)) => {
+            assert!(matches!(e.data(), ParserErrorData::ImportError));
+            assert!(e.message().contains("'lib:build'"), "{}", e);
+        }
+        _ => panic!("duplicate name was accepted"),
+    }
+}
+
+#[test]
+fn validator_reports_duplicate_names() {
+    let mut task_file = reader::read(String::from(TASKS)).unwrap();
+    let test = task_file.tasks.get_mut("test").unwrap();
+    test.aliases.push(String::from("b"));
+
+    let diagnostics = validate(&task_file, Utf8Path::new("."));
+
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].location, "task 'test'");
+    assert_eq!(
+        diagnostics[0].message,
+        "Name or alias 'b' is already used by task 'build'"
+    );
+}
+
+#[test]
+fn imports_are_resolved_relative_to_the_given_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = Utf8Path::from_path(dir.path()).unwrap();
+    fs::create_dir(dir.join("lib")).unwrap();
+    fs::write(
+        dir.join("lib/tasks.kdl"),
+        r#"import "lib/more.kdl" prefix="more""#,
+    )
+    .unwrap();
+    fs::write(dir.join("lib/more.kdl"), r#"tasks { build { - "make"; } }"#).unwrap();
+
+    let task_file =
+        reader::read_in(String::from(r#"import "lib/tasks.kdl" prefix="lib""#), dir).unwrap();
+
+    assert!(task_file.tasks.contains_key("lib:more:build"));
+    assert_eq!(task_file.imports[0].path, "lib/tasks.kdl");
+}
+
+#[test]
+fn map_variables_keep_reserved_names_as_entries() {
+    let task_file = reader::read(String::from(
+        r#"
+        variables {
+            server {
+                host "localhost"
+                cmd "ssh"
+                enum "a"
+                prompt "Which?"
+                cd "/srv"
+                task "deploy"
+            }
+            version {
+                cmd "git describe"
+            }
+        }
+        "#,
+    ))
+    .unwrap();
+
+    let VariableValue::Static(Value::Map(server)) = &task_file.variables[0].value else {
+        panic!("unexpected value {:?}", task_file.variables[0].value);
+    };
+    assert_eq!(
+        server.keys().collect::<Vec<_>>(),
+        vec!["host", "cmd", "enum", "prompt", "cd", "task"]
+    );
+    assert_eq!(server["cd"], Value::String(String::from("/srv")));
+    assert!(task_file.variables[0].constraints.choices.is_empty());
+    assert!(matches!(
+        task_file.variables[1].value,
+        VariableValue::Action(Action::Command(_))
+    ));
+
+    let mixed = reader::read(String::from(
+        r#"variables { server { host "localhost"; - "item"; } }"#,
+    ));
+    assert!(mixed.is_err());
+}
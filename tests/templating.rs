@@ -0,0 +1,158 @@
+use camino::Utf8Path;
+use jatr::runner::templating::Templating;
+use jatr::tasks::Value;
+use std::fs;
+
+/// Renders each template in a fresh working directory holding `files`, empty files unless
+/// they end in `/`, which makes them directories
+fn check(files: &[&str], cases: &[(&str, Result<&str, &str>)]) {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = Utf8Path::from_path(dir.path()).unwrap();
+    for file in files {
+        let path = dir.join(file);
+        match file.strip_suffix('/') {
+            Some(_) => fs::create_dir_all(&path).unwrap(),
+            None => {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, *file).unwrap();
+            }
+        }
+    }
+
+    let mut templating = Templating::default();
+    templating.work_dir(dir.as_str());
+    templating.add_variable(
+        "version",
+        Value::String(String::from("v1.4.2-rc.1+build.7")),
+    );
+
+    for (template, expected) in cases {
+        let result = templating.process(template);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap(), *expected, "{}", template),
+            Err(message) => {
+                let error = format!("{:?}", result.expect_err(template));
+                assert!(error.contains(message), "{}: {}", template, error);
+            }
+        }
+    }
+}
+
+#[test]
+fn shell_quote_filter() {
+    check(
+        &[],
+        &[
+            (
+                r#"{{ "plain-word_1.2/x" | shell_quote }}"#,
+                Ok("plain-word_1.2/x"),
+            ),
+            (r#"{{ "two words" | shell_quote }}"#, Ok("'two words'")),
+            (r#"{{ "it's" | shell_quote }}"#, Ok(r"'it'\''s'")),
+            (r#"{{ "" | shell_quote }}"#, Ok("''")),
+            (r#"{{ "$HOME;rm" | shell_quote }}"#, Ok("'$HOME;rm'")),
+            ("{{ 42 | shell_quote }}", Ok("42")),
+        ],
+    );
+}
+
+#[test]
+fn sha256_filter() {
+    check(
+        &[],
+        &[
+            (
+                r#"{{ "" | sha256 }}"#,
+                Ok("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            ),
+            (
+                r#"{{ "abc" | sha256 }}"#,
+                Ok("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn semver_bump_filter() {
+    check(
+        &[],
+        &[
+            (r#"{{ "1.2.3" | semver_bump }}"#, Ok("1.2.4")),
+            (r#"{{ "1.2.3" | semver_bump(part="minor") }}"#, Ok("1.3.0")),
+            (r#"{{ "1.2.3" | semver_bump(part="major") }}"#, Ok("2.0.0")),
+            (r#"{{ version | semver_bump(part="minor") }}"#, Ok("v1.5.0")),
+            (
+                r#"{{ "1.2.3" | semver_bump(part="build") }}"#,
+                Err("Unknown version part 'build'"),
+            ),
+            (
+                r#"{{ "latest" | semver_bump }}"#,
+                Err("Cannot bump 'latest'"),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn exists_function() {
+    check(
+        &["Cargo.lock", "src/"],
+        &[
+            (r#"{{ exists(path="Cargo.lock") }}"#, Ok("true")),
+            (r#"{{ exists(path="src") }}"#, Ok("true")),
+            (r#"{{ exists(path="missing.txt") }}"#, Ok("false")),
+            ("{{ exists(file=1) }}", Err("missing argument 'path'")),
+            ("{{ exists(path=1) }}", Err("should be a string")),
+        ],
+    );
+}
+
+#[test]
+fn glob_function() {
+    check(
+        &[
+            "src/main.rs",
+            "src/lib.rs",
+            "src/nested/mod.rs",
+            "README.md",
+        ],
+        &[
+            (
+                r#"{{ glob(pattern="src/**/*.rs") | join(sep=",") }}"#,
+                Ok("src/lib.rs,src/main.rs,src/nested/mod.rs"),
+            ),
+            (
+                r#"{{ glob(pattern="*.md") | join(sep=",") }}"#,
+                Ok("README.md"),
+            ),
+            (r#"{{ glob(pattern="*.toml") | length }}"#, Ok("0")),
+        ],
+    );
+}
+
+#[test]
+fn read_file_env_and_which_functions() {
+    std::env::set_var("JATR_TEST_TEMPLATE_VALUE", "from env");
+
+    check(
+        &["VERSION"],
+        &[
+            (r#"{{ read_file(path="VERSION") }}"#, Ok("VERSION")),
+            (r#"{{ read_file(path="missing") }}"#, Err("Cannot read")),
+            (
+                r#"{{ env(name="JATR_TEST_TEMPLATE_VALUE") }}"#,
+                Ok("from env"),
+            ),
+            (
+                r#"{{ env(name="JATR_TEST_UNSET", default="none") }}"#,
+                Ok("none"),
+            ),
+            (
+                r#"{{ env(name="JATR_TEST_UNSET") }}"#,
+                Err("is not set and has no default"),
+            ),
+            (r#"{{ which(name="jatr-no-such-program") }}"#, Ok("")),
+        ],
+    );
+}
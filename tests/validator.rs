@@ -0,0 +1,40 @@
+use camino::Utf8Path;
+use jatr::reader;
+use jatr::tasks::{Requirement, RequirementKind};
+use jatr::validator::{validate, Severity};
+
+#[test]
+fn reports_invalid_requirements() {
+    let mut task_file =
+        reader::read(String::from(r#"tasks { build { - "cargo build" } }"#)).unwrap();
+    let build = task_file.tasks.get_mut("build").unwrap();
+    build.requirements = vec![
+        Requirement {
+            kind: RequirementKind::Tool {
+                name: String::from("cargo"),
+                version: Some(String::from(">=one")),
+            },
+            hint: None,
+        },
+        Requirement {
+            kind: RequirementKind::Env(String::from("A=B")),
+            hint: None,
+        },
+        Requirement {
+            kind: RequirementKind::Tool {
+                name: String::from("rustc"),
+                version: Some(String::from(">=1.70, <2")),
+            },
+            hint: None,
+        },
+    ];
+
+    let diagnostics = validate(&task_file, Utf8Path::new("."));
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(messages[0].starts_with("Invalid version requirement '>=one' of tool 'cargo'"));
+    assert_eq!(messages[1], "Invalid environment variable name 'A=B'");
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+    assert!(diagnostics.iter().all(|d| d.location == "task 'build'"));
+}
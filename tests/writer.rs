@@ -0,0 +1,133 @@
+use jatr::{reader, writer, TaskFile};
+use kdl::KdlDocument;
+
+fn format(content: &str) -> String {
+    let mut document: KdlDocument = content.parse().unwrap();
+    jatr::writer::format(&mut document);
+    document.to_string()
+}
+
+#[test]
+fn keeps_variables_before_import() {
+    let formatted = format(
+        "tasks {\n    build { - \"make\"; }\n}\nvariables {\n    mode \"debug\"\n}\nimport \"common.kdl\"\n",
+    );
+
+    assert_eq!(
+        formatted,
+        "variables {\n    mode \"debug\"\n}\n\nimport \"common.kdl\"\n\ntasks {\n    build {\n        cmd \"make\"\n    }\n}\n"
+    );
+}
+
+#[test]
+fn keeps_import_before_variables() {
+    let content = "import \"common.kdl\"\n\nvariables {\n    mode \"debug\"\n}\n";
+
+    assert_eq!(format(content), content);
+}
+
+const SOURCE: &str = r#"// Shared settings
+variables {
+    // Build profile
+    profile "debug"
+    target "x86_64" // host
+    jobs 4
+}
+
+tasks {
+    // Compile
+    build {
+        cmd "cargo build" // fast
+    }
+
+    // Check
+    test {
+        cmd "cargo test"
+    }
+
+    lint {
+        cmd "cargo clippy"
+    }
+}
+"#;
+
+/// Applies `change` to the task file read from `SOURCE` and updates the document with it
+fn update(change: impl FnOnce(&mut TaskFile)) -> String {
+    let mut document: KdlDocument = SOURCE.parse().unwrap();
+    let mut task_file = reader::read(String::from(SOURCE)).unwrap();
+
+    change(&mut task_file);
+    writer::update(&mut document, &task_file);
+    document.to_string()
+}
+
+#[test]
+fn update_without_changes_keeps_document() {
+    assert_eq!(update(|_| {}), SOURCE);
+}
+
+#[test]
+fn update_removes_task() {
+    let updated = update(|task_file| {
+        task_file.tasks.shift_remove("test");
+    });
+
+    assert_eq!(
+        updated,
+        SOURCE.replace(
+            "    // Check\n    test {\n        cmd \"cargo test\"\n    }\n\n",
+            ""
+        )
+    );
+}
+
+#[test]
+fn update_renames_task_in_place() {
+    let updated = update(|task_file| {
+        let (index, _, mut task) = task_file.tasks.shift_remove_full("test").unwrap();
+        task.name = String::from("check");
+        task_file.tasks.shift_insert(index, task.name.clone(), task);
+    });
+
+    assert_eq!(updated, SOURCE.replace("    test {", "    check {"));
+}
+
+#[test]
+fn update_keeps_document_order_of_reordered_tasks() {
+    let updated = update(|task_file| {
+        task_file.tasks.move_index(2, 0);
+        task_file.tasks["lint"].aliases.push(String::from("l"));
+    });
+
+    assert_eq!(
+        updated,
+        SOURCE.replace("    lint {\n", "    lint {\n        alias \"l\"\n")
+    );
+}
+
+#[test]
+fn update_removes_variable() {
+    let updated = update(|task_file| task_file.variables.retain(|v| v.name != "target"));
+
+    assert_eq!(
+        updated,
+        SOURCE.replace("    target \"x86_64\" // host\n", "")
+    );
+}
+
+#[test]
+fn update_renames_variable_in_place() {
+    let updated = update(|task_file| task_file.variables[1].name = String::from("arch"));
+
+    assert_eq!(
+        updated,
+        SOURCE.replace("    target \"x86_64\"", "    arch \"x86_64\"")
+    );
+}
+
+#[test]
+fn update_keeps_document_order_of_reordered_variables() {
+    let updated = update(|task_file| task_file.variables.swap(0, 2));
+
+    assert_eq!(updated, SOURCE);
+}